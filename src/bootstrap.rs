@@ -0,0 +1,382 @@
+use super::random::Rng;
+use super::statistics::{percentile_key, quantile, quantile_at, Interpolation, Percentiles};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_RESAMPLES: usize = 1000;
+const MAX_RESAMPLES: usize = 100_000;
+const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+/// Options for bootstrap confidence intervals
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "RawBootstrapOptions")]
+pub struct BootstrapOptions {
+    /// Number of resamples (default 1000, at most 100000)
+    pub resamples: usize,
+    /// Confidence level strictly between 0 and 1 (default 0.95)
+    pub confidence: f64,
+    pub method: BootstrapMethod,
+    /// PRNG seed; the same seed and input give the same resamples on every target
+    pub seed: u64,
+}
+
+/// How interval bounds are read from the bootstrap distribution
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BootstrapMethod {
+    /// Plain percentiles of the bootstrap distribution
+    #[default]
+    Percentile,
+    /// Bias-corrected and accelerated percentiles (Efron 1987), using jackknife acceleration
+    #[serde(alias = "BCa")]
+    Bca,
+}
+
+impl Default for BootstrapOptions {
+    fn default() -> Self {
+        BootstrapOptions {
+            resamples: DEFAULT_RESAMPLES,
+            confidence: DEFAULT_CONFIDENCE,
+            method: BootstrapMethod::default(),
+            seed: 0,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBootstrapOptions {
+    resamples: Option<usize>,
+    confidence: Option<f64>,
+    #[serde(default)]
+    method: BootstrapMethod,
+    #[serde(default)]
+    seed: u64,
+}
+
+impl TryFrom<RawBootstrapOptions> for BootstrapOptions {
+    type Error = String;
+
+    fn try_from(raw: RawBootstrapOptions) -> Result<Self, Self::Error> {
+        let resamples = raw.resamples.unwrap_or(DEFAULT_RESAMPLES);
+        if !(1..=MAX_RESAMPLES).contains(&resamples) {
+            return Err(format!("resamples must be between 1 and {}", MAX_RESAMPLES));
+        }
+        let confidence = raw.confidence.unwrap_or(DEFAULT_CONFIDENCE);
+        if !(confidence > 0.0 && confidence < 1.0) {
+            return Err("confidence must be strictly between 0 and 1".to_string());
+        }
+        Ok(BootstrapOptions {
+            resamples,
+            confidence,
+            method: raw.method,
+            seed: raw.seed,
+        })
+    }
+}
+
+/// Two-sided confidence interval
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConfidenceInterval {
+    pub lower: f64,
+    pub upper: f64,
+}
+
+/// Confidence intervals for the location metrics of a `StatisticsResult`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfidenceIntervals {
+    pub confidence: f64,
+    pub method: BootstrapMethod,
+    pub resamples: usize,
+    pub mean: ConfidenceInterval,
+    pub median: ConfidenceInterval,
+    pub percentiles: Percentiles<ConfidenceInterval>,
+}
+
+/// Bootstrap the mean, median and each of `quantiles` over ascending, non-empty `sorted` values
+pub fn bootstrap_intervals(
+    sorted: &[f64],
+    quantiles: &[f64],
+    interpolation: Interpolation,
+    options: &BootstrapOptions,
+) -> ConfidenceIntervals {
+    let n = sorted.len();
+    let observed = estimates(n, |i| sorted[i], quantiles, interpolation);
+
+    // replicates[k] holds every resample's value of statistic k
+    let mut replicates = vec![Vec::with_capacity(options.resamples); observed.len()];
+    let mut rng = Rng::new(options.seed);
+    let mut sample = vec![0.0; n];
+    for _ in 0..options.resamples {
+        for slot in sample.iter_mut() {
+            *slot = sorted[rng.below(n as u64) as usize];
+        }
+        sample.sort_by(|a, b| a.total_cmp(b));
+        let resampled = estimates(n, |i| sample[i], quantiles, interpolation);
+        for (statistic, value) in replicates.iter_mut().zip(resampled) {
+            statistic.push(value);
+        }
+    }
+
+    let accelerations = match options.method {
+        BootstrapMethod::Percentile => vec![0.0; observed.len()],
+        BootstrapMethod::Bca => jackknife_accelerations(sorted, quantiles, interpolation),
+    };
+
+    let mut intervals = replicates
+        .iter_mut()
+        .zip(&observed)
+        .zip(accelerations)
+        .map(|((statistic, observed), acceleration)| {
+            statistic.sort_by(|a, b| a.total_cmp(b));
+            interval(statistic, *observed, acceleration, options)
+        });
+
+    let mean = intervals.next().unwrap_or_default();
+    let median = intervals.next().unwrap_or_default();
+    let percentiles = Percentiles(
+        quantiles
+            .iter()
+            .map(|q| percentile_key(*q))
+            .zip(intervals)
+            .collect(),
+    );
+
+    ConfidenceIntervals {
+        confidence: options.confidence,
+        method: options.method,
+        resamples: options.resamples,
+        mean,
+        median,
+        percentiles,
+    }
+}
+
+/// Mean, median, then each requested quantile of `len` ascending values
+fn estimates(
+    len: usize,
+    value_at: impl Fn(usize) -> f64,
+    quantiles: &[f64],
+    interpolation: Interpolation,
+) -> Vec<f64> {
+    let mean = (0..len).map(&value_at).sum::<f64>() / len as f64;
+    let median = quantile_at(len, &value_at, 0.5, Interpolation::Linear);
+    let mut values = vec![mean, median];
+    values.extend(quantiles.iter().map(|q| quantile_at(len, &value_at, *q, interpolation)));
+    values
+}
+
+/// BCa acceleration per statistic from leave-one-out estimates
+fn jackknife_accelerations(
+    sorted: &[f64],
+    quantiles: &[f64],
+    interpolation: Interpolation,
+) -> Vec<f64> {
+    let n = sorted.len();
+    let statistics = quantiles.len() + 2;
+    if n < 3 {
+        return vec![0.0; statistics];
+    }
+
+    let sum: f64 = sorted.iter().sum();
+    let leave_one_out: Vec<Vec<f64>> = (0..n)
+        .map(|skip| {
+            let value_at = |i: usize| if i < skip { sorted[i] } else { sorted[i + 1] };
+            let mut values = vec![
+                (sum - sorted[skip]) / (n - 1) as f64,
+                quantile_at(n - 1, value_at, 0.5, Interpolation::Linear),
+            ];
+            values.extend(quantiles.iter().map(|q| quantile_at(n - 1, value_at, *q, interpolation)));
+            values
+        })
+        .collect();
+
+    (0..statistics)
+        .map(|k| {
+            let mean = leave_one_out.iter().map(|values| values[k]).sum::<f64>() / n as f64;
+            let (mut squares, mut cubes) = (0.0, 0.0);
+            for values in &leave_one_out {
+                let d = mean - values[k];
+                squares += d * d;
+                cubes += d * d * d;
+            }
+            if squares == 0.0 {
+                0.0
+            } else {
+                cubes / (6.0 * squares.powf(1.5))
+            }
+        })
+        .collect()
+}
+
+/// Interval from one statistic's ascending bootstrap distribution
+fn interval(
+    replicates: &[f64],
+    observed: f64,
+    acceleration: f64,
+    options: &BootstrapOptions,
+) -> ConfidenceInterval {
+    let alpha = (1.0 - options.confidence) / 2.0;
+    let (lower_level, upper_level) = match options.method {
+        BootstrapMethod::Percentile => (alpha, 1.0 - alpha),
+        BootstrapMethod::Bca => {
+            let resamples = replicates.len() as f64;
+            let below = replicates.iter().filter(|value| **value < observed).count() as f64;
+            let ties = replicates.iter().filter(|value| **value == observed).count() as f64;
+            let proportion = ((below + ties / 2.0) / resamples)
+                .clamp(0.5 / resamples, 1.0 - 0.5 / resamples);
+            let bias = normal_quantile(proportion);
+            let adjust = |level: f64| {
+                let z = bias + normal_quantile(level);
+                let adjusted = normal_cdf(bias + z / (1.0 - acceleration * z));
+                if adjusted.is_finite() {
+                    adjusted
+                } else {
+                    level
+                }
+            };
+            (adjust(alpha), adjust(1.0 - alpha))
+        }
+    };
+
+    ConfidenceInterval {
+        lower: quantile(replicates, lower_level, Interpolation::Linear),
+        upper: quantile(replicates, upper_level, Interpolation::Linear),
+    }
+}
+
+/// Standard normal CDF via the complementary error function (Numerical Recipes erfcc, |error| < 1.2e-7)
+fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let erfc = t * poly.exp();
+    if x >= 0.0 {
+        1.0 - erfc / 2.0
+    } else {
+        erfc / 2.0
+    }
+}
+
+/// Standard normal quantile for `p` in (0, 1) (Acklam's rational approximation, relative error < 1.2e-9)
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969_683_028_665_376e1,
+        2.209_460_984_245_205e2,
+        -2.759_285_104_469_687e2,
+        1.383_577_518_672_69e2,
+        -3.066_479_806_614_716e1,
+        2.506_628_277_459_239,
+    ];
+    const B: [f64; 5] = [
+        -5.447_609_879_822_406e1,
+        1.615_858_368_580_409e2,
+        -1.556_989_798_598_866e2,
+        6.680_131_188_771_972e1,
+        -1.328_068_155_288_572e1,
+    ];
+    const C: [f64; 6] = [
+        -7.784_894_002_430_293e-3,
+        -3.223_964_580_411_365e-1,
+        -2.400_758_277_161_838,
+        -2.549_732_539_343_734,
+        4.374_664_141_464_968,
+        2.938_163_982_698_783,
+    ];
+    const D: [f64; 4] = [
+        7.784_695_709_041_462e-3,
+        3.224_671_290_700_398e-1,
+        2.445_134_137_142_996,
+        3.754_408_661_907_416,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(json: &str) -> BootstrapOptions {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn data() -> Vec<f64> {
+        let mut values: Vec<f64> = (0..60)
+            .map(|i| f64::from((i * 37) % 11) + f64::from(i) / 10.0)
+            .collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        values
+    }
+
+    #[test]
+    fn test_normal_helpers() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.959_964) - 0.975).abs() < 1e-6);
+        assert!((normal_quantile(0.975) - 1.959_964).abs() < 1e-6);
+        assert!((normal_quantile(0.01) + 2.326_348).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_percentile_intervals_cover_estimates_and_are_reproducible() {
+        let values = data();
+        let opts = options(r#"{"seed": 7, "resamples": 500}"#);
+        let first = bootstrap_intervals(&values, &[0.9], Interpolation::Linear, &opts);
+        let second = bootstrap_intervals(&values, &[0.9], Interpolation::Linear, &opts);
+        assert_eq!(first, second);
+
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!(first.mean.lower < mean && mean < first.mean.upper);
+        assert!(first.median.lower <= first.median.upper);
+        assert_eq!(first.percentiles.0[0].0, "p90");
+
+        let opts = options(r#"{"seed": 8, "resamples": 500}"#);
+        let other = bootstrap_intervals(&values, &[0.9], Interpolation::Linear, &opts);
+        assert_ne!(first.mean, other.mean);
+    }
+
+    #[test]
+    fn test_bca_intervals() {
+        let values = data();
+        let bca_options = options(r#"{"method": "bca", "resamples": 2000}"#);
+        let bca = bootstrap_intervals(&values, &[], Interpolation::Linear, &bca_options);
+        let plain_options = options(r#"{"resamples": 2000}"#);
+        let plain = bootstrap_intervals(&values, &[], Interpolation::Linear, &plain_options);
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        assert!(bca.mean.lower < mean && mean < bca.mean.upper);
+        // Roughly symmetric data: BCa stays close to the percentile interval
+        assert!((bca.mean.lower - plain.mean.lower).abs() < 0.3);
+        assert!((bca.mean.upper - plain.mean.upper).abs() < 0.3);
+    }
+
+    #[test]
+    fn test_degenerate_input_and_validation() {
+        let bca_options = options(r#"{"method": "bca"}"#);
+        let single = bootstrap_intervals(&[4.0], &[0.5], Interpolation::Linear, &bca_options);
+        assert_eq!(single.mean, ConfidenceInterval { lower: 4.0, upper: 4.0 });
+        assert!(serde_json::from_str::<BootstrapOptions>(r#"{"confidence": 1}"#).is_err());
+        assert!(serde_json::from_str::<BootstrapOptions>(r#"{"resamples": 0}"#).is_err());
+    }
+}
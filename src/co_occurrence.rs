@@ -1,10 +1,192 @@
 use super::{Reflection, CoOccurrence};
+use super::filter::{apply_filter, ReflectionFilter};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-/// Compute emotion co-occurrence matrix
-pub fn compute_co_occurrence(reflections: &[Reflection]) -> Vec<CoOccurrence> {
-    let mut co_occurrence_map: HashMap<String, usize> = HashMap::new();
-    let total = reflections.len();
+/// Options for co-occurrence computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoOccurrenceOptions {
+    /// Attach `association` metrics to every pair
+    #[serde(default)]
+    pub include_association: bool,
+    /// Key pairs are ranked by, highest first (default count)
+    #[serde(default)]
+    pub rank_by: CoOccurrenceRanking,
+    /// Number of pairs returned: a positive integer or "all" (default 20)
+    #[serde(default)]
+    pub top_n: TopN,
+    /// Pairs seen in fewer reflections are dropped (default 1)
+    pub min_count: Option<usize>,
+    /// Pairs seen in less than this percentage of reflections are dropped (0-100, default 0)
+    #[serde(default, deserialize_with = "deserialize_min_percentage")]
+    pub min_percentage: f64,
+    /// Weight each pair's `count` by how often both emotions are listed in a
+    /// reflection instead of counting it once; thresholds and `percentage`
+    /// still count reflections
+    #[serde(default)]
+    pub count_multiplicity: bool,
+    /// With `countMultiplicity`, also pair an emotion listed twice with itself.
+    /// Self pairs carry no `association`.
+    #[serde(default)]
+    pub include_self_pairs: bool,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+const DEFAULT_TOP_PAIRS: usize = 20;
+
+/// Cap on the number of pairs returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawTopN")]
+pub enum TopN {
+    Limit(usize),
+    All,
+}
+
+impl Default for TopN {
+    fn default() -> Self {
+        TopN::Limit(DEFAULT_TOP_PAIRS)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawTopN {
+    Limit(usize),
+    Keyword(String),
+}
+
+impl TryFrom<RawTopN> for TopN {
+    type Error = String;
+
+    fn try_from(raw: RawTopN) -> Result<Self, Self::Error> {
+        match raw {
+            RawTopN::Limit(0) => Err("topN must be at least 1; use \"all\" for no limit".to_string()),
+            RawTopN::Limit(n) => Ok(TopN::Limit(n)),
+            RawTopN::Keyword(keyword) if keyword == "all" => Ok(TopN::All),
+            RawTopN::Keyword(keyword) => Err(format!(
+                "invalid topN `{}`: expected a positive integer or \"all\"",
+                keyword
+            )),
+        }
+    }
+}
+
+fn deserialize_min_percentage<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let min_percentage = f64::deserialize(deserializer)?;
+    if !(0.0..=100.0).contains(&min_percentage) {
+        return Err(serde::de::Error::custom("minPercentage must be between 0 and 100"));
+    }
+    Ok(min_percentage)
+}
+
+/// Ranking key for co-occurrence pairs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CoOccurrenceRanking {
+    #[default]
+    Count,
+    Lift,
+    Pmi,
+    Npmi,
+    Jaccard,
+}
+
+/// Association strength of a pair, relative to how common each emotion is.
+/// Probabilities are over the (filtered) reflections analysed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssociationMetrics {
+    /// Reflections containing each emotion of the pair, in pair order
+    pub marginal_counts: [usize; 2],
+    /// P(A,B) / (P(A) P(B)); above 1 when the pair appears more often than chance
+    pub lift: f64,
+    /// Pointwise mutual information, ln(lift)
+    pub pmi: f64,
+    /// PMI divided by -ln P(A,B), in [-1, 1]
+    pub npmi: f64,
+    /// count / (reflections containing either emotion)
+    pub jaccard: f64,
+    /// P(second | first)
+    pub second_given_first: f64,
+    /// P(first | second)
+    pub first_given_second: f64,
+}
+
+impl AssociationMetrics {
+    /// `count` is the number of reflections containing both emotions
+    fn new(count: usize, marginal_counts: [usize; 2], total: usize) -> Self {
+        let [first, second] = marginal_counts;
+        let joint = count as f64 / total as f64;
+        // Multiply in f64: usize products overflow on 32-bit wasm
+        let lift = (count as f64 * total as f64) / (first as f64 * second as f64);
+        let pmi = lift.ln();
+        // A pair present in every reflection is perfectly associated, one
+        // never seen together perfectly dissociated
+        let npmi = if joint >= 1.0 {
+            1.0
+        } else if count == 0 {
+            -1.0
+        } else {
+            pmi / -joint.ln()
+        };
+
+        AssociationMetrics {
+            marginal_counts,
+            lift,
+            pmi,
+            npmi,
+            jaccard: count as f64 / (first as f64 + second as f64 - count as f64),
+            second_given_first: count as f64 / first as f64,
+            first_given_second: count as f64 / second as f64,
+        }
+    }
+
+    fn rank_key(&self, ranking: CoOccurrenceRanking) -> f64 {
+        match ranking {
+            CoOccurrenceRanking::Count => 0.0,
+            CoOccurrenceRanking::Lift => self.lift,
+            CoOccurrenceRanking::Pmi => self.pmi,
+            CoOccurrenceRanking::Npmi => self.npmi,
+            CoOccurrenceRanking::Jaccard => self.jaccard,
+        }
+    }
+}
+
+/// Pair and per-emotion tallies over a set of reflections
+pub struct PairCounts {
+    /// (first, second) in id order -> (weighted count, reflections containing both)
+    pub pairs: HashMap<(String, String), (usize, usize)>,
+    /// Reflections containing each emotion
+    pub marginals: HashMap<String, usize>,
+    /// Reflections analysed
+    pub total: usize,
+}
+
+impl PairCounts {
+    /// Association of a pair seen together in `support` reflections
+    pub fn association(&self, first: &str, second: &str, support: usize) -> AssociationMetrics {
+        let marginal_counts = [
+            self.marginals.get(first).copied().unwrap_or(0),
+            self.marginals.get(second).copied().unwrap_or(0),
+        ];
+        AssociationMetrics::new(support, marginal_counts, self.total)
+    }
+}
+
+/// Tally emotion pairs, counting each emotion once per reflection unless
+/// `count_multiplicity` is set
+pub fn count_pairs(
+    reflections: &[Reflection],
+    count_multiplicity: bool,
+    include_self_pairs: bool,
+) -> PairCounts {
+    let mut pairs: HashMap<(String, String), (usize, usize)> = HashMap::new();
+    let mut marginals: HashMap<String, usize> = HashMap::new();
 
     for reflection in reflections {
         let mut emotions: Vec<String> = Vec::new();
@@ -19,55 +201,117 @@ pub fn compute_co_occurrence(reflections: &[Reflection]) -> Vec<CoOccurrence> {
             emotions.extend_from_slice(related);
         }
 
-        // Generate pairs
-        for i in 0..emotions.len() {
-            for j in (i + 1)..emotions.len() {
-                let mut pair = [emotions[i].clone(), emotions[j].clone()];
-                pair.sort(); // Ensure consistent ordering
-                // Use a delimiter that cannot appear in emotion IDs
-                let key = format!("{}|||{}", pair[0], pair[1]);
-                *co_occurrence_map.entry(key).or_insert(0) += 1;
+        // Distinct emotions with how often each is listed
+        emotions.sort();
+        let mut listed: Vec<(String, usize)> = Vec::with_capacity(emotions.len());
+        for emotion in emotions {
+            match listed.last_mut() {
+                Some((last, times)) if *last == emotion => *times += 1,
+                _ => listed.push((emotion, 1)),
+            }
+        }
+
+        // Marginals count reflections, however often an emotion is listed
+        for (emotion, _) in &listed {
+            *marginals.entry(emotion.clone()).or_insert(0) += 1;
+        }
+
+        // Generate pairs; `listed` is sorted, so each pair is already in order
+        for (i, (first, first_times)) in listed.iter().enumerate() {
+            if count_multiplicity && include_self_pairs && *first_times > 1 {
+                let entry = pairs.entry((first.clone(), first.clone())).or_insert((0, 0));
+                entry.0 += first_times * (first_times - 1) / 2;
+                entry.1 += 1;
+            }
+            for (second, second_times) in &listed[i + 1..] {
+                let weight = if count_multiplicity { first_times * second_times } else { 1 };
+                let entry = pairs.entry((first.clone(), second.clone())).or_insert((0, 0));
+                entry.0 += weight;
+                entry.1 += 1;
             }
         }
     }
 
-    let mut result: Vec<CoOccurrence> = co_occurrence_map
-        .into_iter()
-        .map(|(key, count)| {
-            let parts: Vec<&str> = key.splitn(2, "|||").collect();
-            let emotion_pair = if parts.len() == 2 {
-                [parts[0].to_string(), parts[1].to_string()]
-            } else {
-                ["unknown".to_string(), "unknown".to_string()]
-            };
+    PairCounts {
+        pairs,
+        marginals,
+        total: reflections.len(),
+    }
+}
+
+/// Compute emotion co-occurrence matrix
+pub fn compute_co_occurrence(
+    reflections: &[Reflection],
+    options: &CoOccurrenceOptions,
+) -> Vec<CoOccurrence> {
+    let reflections = apply_filter(reflections, options.filter.as_ref(), None);
+    let counts = count_pairs(&reflections, options.count_multiplicity, options.include_self_pairs);
+    let total = counts.total;
 
-            CoOccurrence {
-                emotion_pair,
+    // Thresholds and percentage are over reflections; only `count` is weighted
+    let min_count = options.min_count.unwrap_or(1);
+    let mut ranked: Vec<(CoOccurrence, Option<AssociationMetrics>)> = counts
+        .pairs
+        .iter()
+        .filter(|(_, (_, support))| *support >= min_count)
+        .map(|((first, second), &(count, support))| {
+            // Association compares two distinct emotions, so self pairs have none
+            let association = (first != second).then(|| counts.association(first, second, support));
+
+            let pair = CoOccurrence {
+                emotion_pair: [first.clone(), second.clone()],
                 count,
                 percentage: if total > 0 {
-                    (count as f64 / total as f64) * 100.0
+                    (support as f64 / total as f64) * 100.0
                 } else {
                     0.0
                 },
-            }
+                association: None,
+            };
+            (pair, association)
         })
+        .filter(|(pair, _)| pair.percentage >= options.min_percentage)
         .collect();
 
-    // Sort by count descending
-    result.sort_by(|a, b| b.count.cmp(&a.count));
-    result.truncate(20); // Top 20 co-occurrences
+    // Sort by the ranking key descending, then by count, then by pair so
+    // equal pairs come out in the same order on every run. Self pairs have no
+    // association and rank last under association keys.
+    let rank_key = |metrics: &Option<AssociationMetrics>| match metrics {
+        Some(metrics) => metrics.rank_key(options.rank_by),
+        None if options.rank_by == CoOccurrenceRanking::Count => 0.0,
+        None => f64::NEG_INFINITY,
+    };
+    ranked.sort_by(|(a, a_metrics), (b, b_metrics)| {
+        rank_key(b_metrics)
+            .total_cmp(&rank_key(a_metrics))
+            .then_with(|| b.count.cmp(&a.count))
+            .then_with(|| a.emotion_pair.cmp(&b.emotion_pair))
+    });
+    if let TopN::Limit(limit) = options.top_n {
+        ranked.truncate(limit);
+    }
 
-    result
+    ranked
+        .into_iter()
+        .map(|(mut pair, association)| {
+            if options.include_association {
+                pair.association = association;
+            }
+            pair
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_reflection as reflection;
 
     #[test]
     fn test_compute_co_occurrence() {
         let reflections = vec![
             Reflection {
+                id: None,
                 timestamp: "2024-01-15T10:00:00Z".to_string(),
                 emotion_id: Some("joy".to_string()),
                 emotion_name: Some("Joy".to_string()),
@@ -81,7 +325,7 @@ mod tests {
             },
         ];
 
-        let result = compute_co_occurrence(&reflections);
+        let result = compute_co_occurrence(&reflections, &CoOccurrenceOptions::default());
         assert!(!result.is_empty());
     }
 
@@ -89,6 +333,7 @@ mod tests {
     fn test_compute_co_occurrence_hyphenated_ids() {
         let reflections = vec![
             Reflection {
+                id: None,
                 timestamp: "2024-01-15T10:00:00Z".to_string(),
                 emotion_id: Some("mixed-joy".to_string()),
                 emotion_name: Some("Mixed Joy".to_string()),
@@ -102,7 +347,7 @@ mod tests {
             },
         ];
 
-        let result = compute_co_occurrence(&reflections);
+        let result = compute_co_occurrence(&reflections, &CoOccurrenceOptions::default());
         assert!(!result.is_empty());
         // Verify hyphenated emotion IDs are preserved correctly
         let pair = &result[0].emotion_pair;
@@ -117,6 +362,7 @@ mod tests {
     fn test_compute_co_occurrence_no_related() {
         let reflections = vec![
             Reflection {
+                id: None,
                 timestamp: "2024-01-15T10:00:00Z".to_string(),
                 emotion_id: Some("joy".to_string()),
                 emotion_name: Some("Joy".to_string()),
@@ -130,8 +376,142 @@ mod tests {
             },
         ];
 
-        let result = compute_co_occurrence(&reflections);
+        let result = compute_co_occurrence(&reflections, &CoOccurrenceOptions::default());
         // No pairs if only one emotion
         assert_eq!(result.len(), 0);
     }
+
+    #[test]
+    fn test_association_metrics() {
+        // joy is everywhere; calm and relief only ever appear together
+        let reflections = vec![
+            reflection("joy", &["calm", "relief"]),
+            reflection("joy", &["calm", "relief"]),
+            reflection("joy", &["pride"]),
+            reflection("joy", &["pride"]),
+        ];
+        let options: CoOccurrenceOptions =
+            serde_json::from_str(r#"{"includeAssociation": true, "rankBy": "lift"}"#).unwrap();
+        let result = compute_co_occurrence(&reflections, &options);
+        assert_eq!(result[0].emotion_pair, ["calm".to_string(), "relief".to_string()]);
+
+        let metrics = result[0].association.unwrap();
+        assert_eq!(metrics.marginal_counts, [2, 2]);
+        assert!((metrics.lift - 2.0).abs() < 1e-12);
+        assert!((metrics.pmi - 2f64.ln()).abs() < 1e-12);
+        assert!((metrics.npmi - 1.0).abs() < 1e-12);
+        assert_eq!(metrics.jaccard, 1.0);
+        assert_eq!((metrics.second_given_first, metrics.first_given_second), (1.0, 1.0));
+
+        let joy_pride = result
+            .iter()
+            .find(|pair| pair.emotion_pair == ["joy".to_string(), "pride".to_string()])
+            .unwrap();
+        let metrics = joy_pride.association.unwrap();
+        assert_eq!(metrics.marginal_counts, [4, 2]);
+        assert!((metrics.lift - 1.0).abs() < 1e-12);
+        assert_eq!(metrics.jaccard, 0.5);
+        assert_eq!((metrics.second_given_first, metrics.first_given_second), (0.5, 1.0));
+    }
+
+    #[test]
+    fn test_association_large_counts() {
+        // Products of the counts exceed usize even though each count fits
+        let n = usize::MAX / 2;
+        let metrics = AssociationMetrics::new(n, [n, n], n);
+        assert_eq!(metrics.lift, 1.0);
+        assert_eq!(metrics.pmi, 0.0);
+        assert_eq!(metrics.jaccard, 1.0);
+    }
+
+    #[test]
+    fn test_top_n_thresholds_and_tie_break() {
+        let reflections: Vec<Reflection> = (0..30)
+            .map(|i| reflection("joy", &[&format!("e{:02}", i)]))
+            .chain(std::iter::once(reflection("joy", &["e05"])))
+            .collect();
+
+        let result = compute_co_occurrence(&reflections, &CoOccurrenceOptions::default());
+        assert_eq!(result.len(), 20);
+        assert_eq!(result[0].emotion_pair[0], "e05");
+        assert_eq!(result[1].emotion_pair[0], "e00");
+        assert_eq!(result[2].emotion_pair[0], "e01");
+
+        let all: CoOccurrenceOptions = serde_json::from_str(r#"{"topN": "all"}"#).unwrap();
+        assert_eq!(compute_co_occurrence(&reflections, &all).len(), 30);
+
+        let min_count: CoOccurrenceOptions = serde_json::from_str(r#"{"minCount": 2}"#).unwrap();
+        assert_eq!(compute_co_occurrence(&reflections, &min_count).len(), 1);
+
+        // e05 is in 2 of 31 reflections (6.45%), every other pair in 1 (3.23%)
+        let min_percentage: CoOccurrenceOptions =
+            serde_json::from_str(r#"{"minPercentage": 5, "topN": 3}"#).unwrap();
+        assert_eq!(compute_co_occurrence(&reflections, &min_percentage).len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_thresholds() {
+        for options in [r#"{"topN": 0}"#, r#"{"topN": "many"}"#, r#"{"minPercentage": 120}"#] {
+            assert!(serde_json::from_str::<CoOccurrenceOptions>(options).is_err(), "{}", options);
+        }
+    }
+
+    #[test]
+    fn test_duplicates_within_reflection() {
+        let reflections = vec![reflection("joy", &["joy", "calm", "calm"])];
+        let result = compute_co_occurrence(&reflections, &CoOccurrenceOptions::default());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].emotion_pair, ["calm".to_string(), "joy".to_string()]);
+        assert_eq!(result[0].count, 1);
+
+        let weighted: CoOccurrenceOptions =
+            serde_json::from_str(r#"{"countMultiplicity": true}"#).unwrap();
+        let result = compute_co_occurrence(&reflections, &weighted);
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].count, 4);
+
+        let with_self: CoOccurrenceOptions =
+            serde_json::from_str(r#"{"countMultiplicity": true, "includeSelfPairs": true, "includeAssociation": true}"#)
+                .unwrap();
+        let result = compute_co_occurrence(&reflections, &with_self);
+        let pairs: Vec<(&str, &str, usize)> = result
+            .iter()
+            .map(|c| (c.emotion_pair[0].as_str(), c.emotion_pair[1].as_str(), c.count))
+            .collect();
+        assert_eq!(pairs, vec![("calm", "joy", 4), ("calm", "calm", 1), ("joy", "joy", 1)]);
+        // Association is over reflections, not weighted counts
+        assert_eq!(result[0].association.unwrap().jaccard, 1.0);
+        assert!(result[1].association.is_none());
+        assert!(result[2].association.is_none());
+
+        let by_lift: CoOccurrenceOptions =
+            serde_json::from_str(r#"{"countMultiplicity": true, "includeSelfPairs": true, "rankBy": "lift"}"#)
+                .unwrap();
+        let result = compute_co_occurrence(&reflections, &by_lift);
+        assert_eq!(result[0].emotion_pair, ["calm".to_string(), "joy".to_string()]);
+    }
+
+    #[test]
+    fn test_thresholds_count_reflections_under_multiplicity() {
+        let reflections = vec![reflection("joy", &["joy", "calm", "calm"]), reflection("fear", &[])];
+        let options: CoOccurrenceOptions =
+            serde_json::from_str(r#"{"countMultiplicity": true, "minCount": 2}"#).unwrap();
+        assert!(compute_co_occurrence(&reflections, &options).is_empty());
+
+        let options: CoOccurrenceOptions =
+            serde_json::from_str(r#"{"countMultiplicity": true, "minPercentage": 60}"#).unwrap();
+        assert!(compute_co_occurrence(&reflections, &options).is_empty());
+
+        let options: CoOccurrenceOptions = serde_json::from_str(r#"{"countMultiplicity": true}"#).unwrap();
+        let result = compute_co_occurrence(&reflections, &options);
+        assert_eq!(result[0].count, 4);
+        assert_eq!(result[0].percentage, 50.0);
+    }
+
+    #[test]
+    fn test_association_omitted_by_default() {
+        let result = compute_co_occurrence(&[reflection("joy", &["calm"])], &CoOccurrenceOptions::default());
+        assert!(result[0].association.is_none());
+        assert!(serde_json::from_str::<CoOccurrenceOptions>(r#"{"rankBy": "support"}"#).is_err());
+    }
 }
@@ -0,0 +1,298 @@
+use super::Reflection;
+use super::co_occurrence::{count_pairs, PairCounts};
+use super::filter::{apply_filter, ReflectionFilter};
+use serde::{Deserialize, Serialize};
+
+/// Options for co-occurrence matrix computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "RawCoOccurrenceMatrixOptions")]
+pub struct CoOccurrenceMatrixOptions {
+    /// Quantity held in each cell (default count)
+    pub value: MatrixValue,
+    /// Divide each row by its sum
+    pub normalize_rows: bool,
+    /// Label order (default frequency)
+    pub order: MatrixOrder,
+    /// Same as in `CoOccurrenceOptions`
+    pub count_multiplicity: bool,
+    /// Same as in `CoOccurrenceOptions`; fills the diagonal of `count` and
+    /// `percentage` matrices, which is `null` otherwise
+    pub include_self_pairs: bool,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawCoOccurrenceMatrixOptions {
+    #[serde(default)]
+    value: MatrixValue,
+    #[serde(default)]
+    normalize_rows: bool,
+    #[serde(default)]
+    order: MatrixOrder,
+    #[serde(default)]
+    count_multiplicity: bool,
+    #[serde(default)]
+    include_self_pairs: bool,
+    filter: Option<ReflectionFilter>,
+}
+
+impl TryFrom<RawCoOccurrenceMatrixOptions> for CoOccurrenceMatrixOptions {
+    type Error = String;
+
+    fn try_from(raw: RawCoOccurrenceMatrixOptions) -> Result<Self, Self::Error> {
+        // Rows of signed values have no meaningful sum to normalize by
+        if raw.normalize_rows && matches!(raw.value, MatrixValue::Pmi | MatrixValue::Npmi) {
+            return Err("normalizeRows is not supported for pmi or npmi values".to_string());
+        }
+        Ok(CoOccurrenceMatrixOptions {
+            value: raw.value,
+            normalize_rows: raw.normalize_rows,
+            order: raw.order,
+            count_multiplicity: raw.count_multiplicity,
+            include_self_pairs: raw.include_self_pairs,
+            filter: raw.filter,
+        })
+    }
+}
+
+/// Quantity held in each matrix cell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatrixValue {
+    #[default]
+    Count,
+    Percentage,
+    Lift,
+    Pmi,
+    Npmi,
+    Jaccard,
+    /// P(column | row)
+    Conditional,
+}
+
+/// Order of the matrix labels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatrixOrder {
+    /// Most frequent emotion first
+    #[default]
+    Frequency,
+    /// Dendrogram leaf order of average-linkage clustering on Jaccard distance
+    Cluster,
+}
+
+/// Dense co-occurrence matrix; row and column `i` both belong to `labels[i]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoOccurrenceMatrix {
+    pub labels: Vec<String>,
+    /// Reflections containing each label
+    pub marginal_counts: Vec<usize>,
+    /// `null` where the value is undefined, e.g. the PMI of a pair never seen together
+    pub matrix: Vec<Vec<Option<f64>>>,
+}
+
+/// Compute a square co-occurrence matrix over every emotion present
+pub fn compute_co_occurrence_matrix(
+    reflections: &[Reflection],
+    options: &CoOccurrenceMatrixOptions,
+) -> CoOccurrenceMatrix {
+    let reflections = apply_filter(reflections, options.filter.as_ref(), None);
+    let counts = count_pairs(&reflections, options.count_multiplicity, options.include_self_pairs);
+
+    let mut labels: Vec<String> = counts.marginals.keys().cloned().collect();
+    labels.sort_by(|a, b| counts.marginals[b].cmp(&counts.marginals[a]).then_with(|| a.cmp(b)));
+    if options.order == MatrixOrder::Cluster {
+        labels = cluster_order(&counts, labels);
+    }
+
+    let matrix = labels
+        .iter()
+        .map(|row| {
+            let mut cells: Vec<Option<f64>> = labels
+                .iter()
+                .map(|column| cell(&counts, row, column, options))
+                .collect();
+            if options.normalize_rows {
+                let sum: f64 = cells.iter().flatten().sum();
+                if sum > 0.0 {
+                    for value in cells.iter_mut().flatten() {
+                        *value /= sum;
+                    }
+                }
+            }
+            cells
+        })
+        .collect();
+
+    CoOccurrenceMatrix {
+        marginal_counts: labels.iter().map(|label| counts.marginals[label]).collect(),
+        labels,
+        matrix,
+    }
+}
+
+/// Weighted count and reflection support of an unordered pair
+fn pair(counts: &PairCounts, a: &str, b: &str) -> (usize, usize) {
+    let key = if a <= b { (a, b) } else { (b, a) };
+    counts
+        .pairs
+        .get(&(key.0.to_string(), key.1.to_string()))
+        .copied()
+        .unwrap_or((0, 0))
+}
+
+/// Diagonal cells hold only counts and percentages of counted self pairs;
+/// association between an emotion and itself is undefined
+fn cell(
+    counts: &PairCounts,
+    row: &str,
+    column: &str,
+    options: &CoOccurrenceMatrixOptions,
+) -> Option<f64> {
+    let (count, support) = pair(counts, row, column);
+    if row == column
+        && !(options.include_self_pairs
+            && matches!(options.value, MatrixValue::Count | MatrixValue::Percentage))
+    {
+        return None;
+    }
+    let association = counts.association(row, column, support);
+    let value = match options.value {
+        MatrixValue::Count => count as f64,
+        MatrixValue::Percentage => (support as f64 / counts.total as f64) * 100.0,
+        MatrixValue::Lift => association.lift,
+        MatrixValue::Pmi => association.pmi,
+        MatrixValue::Npmi => association.npmi,
+        MatrixValue::Jaccard => association.jaccard,
+        MatrixValue::Conditional => association.second_given_first,
+    };
+    value.is_finite().then_some(value)
+}
+
+/// Reorder `labels` (given in frequency order) by average-linkage
+/// agglomerative clustering, so similar emotions sit next to each other
+fn cluster_order(counts: &PairCounts, labels: Vec<String>) -> Vec<String> {
+    let n = labels.len();
+    let mut distance = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in (i + 1)..n {
+            let (_, support) = pair(counts, &labels[i], &labels[j]);
+            let d = 1.0 - counts.association(&labels[i], &labels[j], support).jaccard;
+            distance[i][j] = d;
+            distance[j][i] = d;
+        }
+    }
+
+    // Each cluster holds its leaves in dendrogram order
+    let mut clusters: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    while clusters.len() > 1 {
+        let mut closest = (0, 1, f64::INFINITY);
+        for a in 0..clusters.len() {
+            for b in (a + 1)..clusters.len() {
+                let total: f64 = clusters[a]
+                    .iter()
+                    .flat_map(|&i| clusters[b].iter().map(move |&j| (i, j)))
+                    .map(|(i, j)| distance[i][j])
+                    .sum();
+                let average = total / (clusters[a].len() * clusters[b].len()) as f64;
+                // Strictly smaller keeps the earliest pair on ties
+                if average < closest.2 {
+                    closest = (a, b, average);
+                }
+            }
+        }
+        let (a, b, _) = closest;
+        let merged = clusters.remove(b);
+        clusters[a].extend(merged);
+    }
+
+    let mut labels: Vec<Option<String>> = labels.into_iter().map(Some).collect();
+    clusters
+        .pop()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|i| labels[i].take())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_reflection as reflection;
+
+    fn sample() -> Vec<Reflection> {
+        vec![
+            reflection("joy", &["pride"]),
+            reflection("joy", &["pride"]),
+            reflection("joy", &[]),
+            reflection("fear", &["anger"]),
+            reflection("fear", &["anger"]),
+        ]
+    }
+
+    #[test]
+    fn test_count_matrix() {
+        let result = compute_co_occurrence_matrix(&sample(), &CoOccurrenceMatrixOptions::default());
+        assert_eq!(result.labels, vec!["joy", "anger", "fear", "pride"]);
+        assert_eq!(result.marginal_counts, vec![3, 2, 2, 2]);
+        assert_eq!(result.matrix[0], vec![None, Some(0.0), Some(0.0), Some(2.0)]);
+        assert_eq!(result.matrix[1][2], Some(2.0));
+        assert_eq!(result.matrix[2][1], Some(2.0));
+    }
+
+    #[test]
+    fn test_cluster_order_groups_related_emotions() {
+        let options: CoOccurrenceMatrixOptions = serde_json::from_str(r#"{"order": "cluster"}"#).unwrap();
+        let result = compute_co_occurrence_matrix(&sample(), &options);
+        let position = |label: &str| result.labels.iter().position(|l| l == label).unwrap();
+        assert_eq!(position("joy").abs_diff(position("pride")), 1);
+        assert_eq!(position("fear").abs_diff(position("anger")), 1);
+    }
+
+    #[test]
+    fn test_metric_values_and_normalization() {
+        let options: CoOccurrenceMatrixOptions =
+            serde_json::from_str(r#"{"value": "conditional"}"#).unwrap();
+        let result = compute_co_occurrence_matrix(&sample(), &options);
+        // P(pride | joy) = 2/3, P(joy | pride) = 1
+        assert!((result.matrix[0][3].unwrap() - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(result.matrix[3][0], Some(1.0));
+
+        let options: CoOccurrenceMatrixOptions = serde_json::from_str(r#"{"value": "pmi"}"#).unwrap();
+        let result = compute_co_occurrence_matrix(&sample(), &options);
+        assert_eq!(result.matrix[0][1], None);
+
+        let options: CoOccurrenceMatrixOptions =
+            serde_json::from_str(r#"{"normalizeRows": true}"#).unwrap();
+        let result = compute_co_occurrence_matrix(&[reflection("joy", &["calm", "fear"])], &options);
+        assert_eq!(result.matrix[0].iter().flatten().sum::<f64>(), 1.0);
+
+        let invalid = r#"{"value": "npmi", "normalizeRows": true}"#;
+        assert!(serde_json::from_str::<CoOccurrenceMatrixOptions>(invalid).is_err());
+    }
+
+    #[test]
+    fn test_diagonal() {
+        let reflections = vec![reflection("joy", &["joy", "calm"]), reflection("calm", &[])];
+        for value in ["count", "npmi", "jaccard", "conditional"] {
+            let options: CoOccurrenceMatrixOptions =
+                serde_json::from_str(&format!(r#"{{"value": "{}"}}"#, value)).unwrap();
+            let result = compute_co_occurrence_matrix(&reflections, &options);
+            assert_eq!((result.matrix[0][0], result.matrix[1][1]), (None, None), "{}", value);
+        }
+
+        let options: CoOccurrenceMatrixOptions =
+            serde_json::from_str(r#"{"countMultiplicity": true, "includeSelfPairs": true}"#).unwrap();
+        let result = compute_co_occurrence_matrix(&reflections, &options);
+        assert_eq!(result.labels, vec!["calm", "joy"]);
+        assert_eq!((result.matrix[0][0], result.matrix[1][1]), (Some(0.0), Some(1.0)));
+
+        let options: CoOccurrenceMatrixOptions =
+            serde_json::from_str(r#"{"value": "npmi", "countMultiplicity": true, "includeSelfPairs": true}"#)
+                .unwrap();
+        assert_eq!(compute_co_occurrence_matrix(&reflections, &options).matrix[1][1], None);
+    }
+}
@@ -0,0 +1,407 @@
+use super::Reflection;
+use super::co_occurrence::count_pairs;
+use super::filter::{apply_filter, ReflectionFilter};
+use super::network::EmotionGraph;
+use super::random::Rng;
+use serde::{Deserialize, Serialize};
+
+const MAX_LABEL_PROPAGATION_ROUNDS: usize = 100;
+
+/// Options for community detection
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunitiesOptions {
+    /// Detection algorithm (default louvain)
+    #[serde(default)]
+    pub algorithm: CommunityAlgorithm,
+    /// Louvain resolution; above 1 favours smaller communities (default 1)
+    #[serde(default, deserialize_with = "deserialize_resolution")]
+    pub resolution: Option<f64>,
+    /// PRNG seed for node visiting order and tie breaks (default 0)
+    #[serde(default)]
+    pub seed: u64,
+    /// Same as in `EmotionNetworkOptions`
+    pub min_count: Option<usize>,
+    /// Same as in `CoOccurrenceOptions`
+    #[serde(default)]
+    pub count_multiplicity: bool,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+fn deserialize_resolution<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let resolution = f64::deserialize(deserializer)?;
+    if !(resolution.is_finite() && resolution > 0.0) {
+        return Err(serde::de::Error::custom("resolution must be a positive number"));
+    }
+    Ok(Some(resolution))
+}
+
+/// Community detection algorithm
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CommunityAlgorithm {
+    #[default]
+    Louvain,
+    LabelPropagation,
+}
+
+/// Group of emotions that tend to appear together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Community {
+    pub id: usize,
+    /// Most frequent emotion first
+    pub members: Vec<String>,
+}
+
+/// Community of one emotion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunityMembership {
+    pub emotion_id: String,
+    pub community: usize,
+}
+
+/// Community detection result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommunitiesResult {
+    /// Largest community first
+    pub communities: Vec<Community>,
+    pub membership: Vec<CommunityMembership>,
+    /// Newman modularity of the partition (resolution 1), 0 without edges
+    pub modularity: f64,
+}
+
+/// Weighted graph with self loops, as Louvain aggregates it
+struct WorkGraph {
+    /// `(neighbour, weight)` pairs, excluding the node itself
+    neighbors: Vec<Vec<(usize, f64)>>,
+    /// Self-loop weight, counted from both ends
+    loops: Vec<f64>,
+}
+
+impl WorkGraph {
+    fn from_emotions(graph: &EmotionGraph) -> Self {
+        WorkGraph {
+            neighbors: graph.neighbors.clone(),
+            loops: vec![0.0; graph.labels.len()],
+        }
+    }
+
+    fn degree(&self, node: usize) -> f64 {
+        self.loops[node] + self.neighbors[node].iter().map(|(_, weight)| weight).sum::<f64>()
+    }
+
+    /// Collapse each community into one node
+    fn aggregate(&self, community: &[usize], count: usize) -> WorkGraph {
+        let mut loops = vec![0.0; count];
+        let mut weights: Vec<Vec<f64>> = vec![vec![0.0; count]; count];
+        for (node, adjacent) in self.neighbors.iter().enumerate() {
+            let c = community[node];
+            loops[c] += self.loops[node];
+            for &(neighbor, weight) in adjacent {
+                let d = community[neighbor];
+                if c == d {
+                    loops[c] += weight;
+                } else {
+                    weights[c][d] += weight;
+                }
+            }
+        }
+        let neighbors = weights
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .enumerate()
+                    .filter(|(_, weight)| *weight > 0.0)
+                    .collect()
+            })
+            .collect();
+        WorkGraph { neighbors, loops }
+    }
+}
+
+/// Detect communities in the emotion co-occurrence graph
+pub fn compute_communities(
+    reflections: &[Reflection],
+    options: &CommunitiesOptions,
+) -> CommunitiesResult {
+    let reflections = apply_filter(reflections, options.filter.as_ref(), None);
+    let counts = count_pairs(&reflections, options.count_multiplicity, false);
+    let graph = EmotionGraph::from_pairs(&counts, options.min_count.unwrap_or(1));
+    let mut rng = Rng::new(options.seed);
+
+    let assignment = match options.algorithm {
+        CommunityAlgorithm::Louvain => louvain(&graph, options.resolution.unwrap_or(1.0), &mut rng),
+        CommunityAlgorithm::LabelPropagation => label_propagation(&graph, &mut rng),
+    };
+    let assignment = renumber(&assignment);
+    let modularity = modularity(&WorkGraph::from_emotions(&graph), &assignment);
+
+    let count = assignment.iter().max().map_or(0, |max| max + 1);
+    let mut communities: Vec<Community> = (0..count)
+        .map(|id| Community {
+            id,
+            members: Vec::new(),
+        })
+        .collect();
+    // Graph labels are in frequency order, so members are too
+    for (node, &community) in assignment.iter().enumerate() {
+        communities[community].members.push(graph.labels[node].clone());
+    }
+
+    let membership = graph
+        .labels
+        .iter()
+        .zip(&assignment)
+        .map(|(emotion_id, &community)| CommunityMembership {
+            emotion_id: emotion_id.clone(),
+            community,
+        })
+        .collect();
+
+    CommunitiesResult {
+        communities,
+        membership,
+        modularity,
+    }
+}
+
+/// Number communities by size, largest first, then by their most frequent member
+fn renumber(assignment: &[usize]) -> Vec<usize> {
+    let mut sizes: Vec<(usize, usize, usize)> = Vec::new(); // (label, size, first node)
+    for (node, &label) in assignment.iter().enumerate() {
+        match sizes.iter_mut().find(|(l, _, _)| *l == label) {
+            Some(entry) => entry.1 += 1,
+            None => sizes.push((label, 1, node)),
+        }
+    }
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.2.cmp(&b.2)));
+    assignment
+        .iter()
+        .map(|label| sizes.iter().position(|(l, _, _)| l == label).unwrap_or(0))
+        .collect()
+}
+
+fn shuffled(n: usize, rng: &mut Rng) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..n).collect();
+    for i in (1..n).rev() {
+        let j = rng.below(i as u64 + 1) as usize;
+        order.swap(i, j);
+    }
+    order
+}
+
+fn modularity(graph: &WorkGraph, community: &[usize]) -> f64 {
+    let n = graph.loops.len();
+    let total: f64 = (0..n).map(|node| graph.degree(node)).sum();
+    if total == 0.0 {
+        return 0.0;
+    }
+    let count = community.iter().max().map_or(0, |max| max + 1);
+    let mut internal = vec![0.0; count];
+    let mut degree = vec![0.0; count];
+    for node in 0..n {
+        let c = community[node];
+        internal[c] += graph.loops[node];
+        degree[c] += graph.degree(node);
+        for &(neighbor, weight) in &graph.neighbors[node] {
+            if community[neighbor] == c {
+                internal[c] += weight;
+            }
+        }
+    }
+    internal
+        .iter()
+        .zip(&degree)
+        .map(|(internal, degree)| internal / total - (degree / total).powi(2))
+        .sum()
+}
+
+/// Louvain method: greedy local moves, then aggregation, until no move helps
+fn louvain(graph: &EmotionGraph, resolution: f64, rng: &mut Rng) -> Vec<usize> {
+    let mut work = WorkGraph::from_emotions(graph);
+    // Community of every original node
+    let mut membership: Vec<usize> = (0..graph.labels.len()).collect();
+    let total: f64 = (0..work.loops.len()).map(|node| work.degree(node)).sum();
+    if total == 0.0 {
+        return membership;
+    }
+
+    loop {
+        let n = work.loops.len();
+        let degrees: Vec<f64> = (0..n).map(|node| work.degree(node)).collect();
+        let mut community: Vec<usize> = (0..n).collect();
+        let mut community_degree = degrees.clone();
+        let mut moved_any = false;
+
+        loop {
+            let mut moved = false;
+            for node in shuffled(n, rng) {
+                let current = community[node];
+                community_degree[current] -= degrees[node];
+
+                // Weight from `node` into each neighbouring community, in first-seen order
+                let mut links: Vec<(usize, f64)> = vec![(current, 0.0)];
+                for &(neighbor, weight) in &work.neighbors[node] {
+                    let c = community[neighbor];
+                    match links.iter_mut().find(|(l, _)| *l == c) {
+                        Some(link) => link.1 += weight,
+                        None => links.push((c, weight)),
+                    }
+                }
+
+                let gain = |(c, weight): (usize, f64)| {
+                    weight - resolution * community_degree[c] * degrees[node] / total
+                };
+                let mut best = (current, gain(links[0]));
+                for &link in &links[1..] {
+                    let link_gain = gain(link);
+                    // Only a strict improvement moves the node, so ties stay put
+                    if link_gain > best.1 + 1e-12 {
+                        best = (link.0, link_gain);
+                    }
+                }
+
+                community_degree[best.0] += degrees[node];
+                if best.0 != current {
+                    community[node] = best.0;
+                    moved = true;
+                    moved_any = true;
+                }
+            }
+            if !moved {
+                break;
+            }
+        }
+
+        if !moved_any {
+            break;
+        }
+        let community = renumber(&community);
+        let count = community.iter().max().map_or(0, |max| max + 1);
+        for c in &mut membership {
+            *c = community[*c];
+        }
+        // Nothing merged, so aggregating would reproduce the same graph
+        if count == n {
+            break;
+        }
+        work = work.aggregate(&community, count);
+    }
+
+    membership
+}
+
+/// Asynchronous label propagation with random visiting order and tie breaks
+fn label_propagation(graph: &EmotionGraph, rng: &mut Rng) -> Vec<usize> {
+    let n = graph.labels.len();
+    let mut labels: Vec<usize> = (0..n).collect();
+
+    for _ in 0..MAX_LABEL_PROPAGATION_ROUNDS {
+        let mut changed = false;
+        for node in shuffled(n, rng) {
+            if graph.neighbors[node].is_empty() {
+                continue;
+            }
+            let mut weights: Vec<(usize, f64)> = Vec::new();
+            for &(neighbor, weight) in &graph.neighbors[node] {
+                let label = labels[neighbor];
+                match weights.iter_mut().find(|(l, _)| *l == label) {
+                    Some(entry) => entry.1 += weight,
+                    None => weights.push((label, weight)),
+                }
+            }
+            let heaviest = weights.iter().map(|(_, weight)| *weight).fold(f64::MIN, f64::max);
+            let candidates: Vec<usize> = weights
+                .iter()
+                .filter(|(_, weight)| *weight == heaviest)
+                .map(|(label, _)| *label)
+                .collect();
+            // Keeping a label that is already among the heaviest avoids oscillation
+            if candidates.contains(&labels[node]) {
+                continue;
+            }
+            labels[node] = candidates[rng.below(candidates.len() as u64) as usize];
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_reflection as reflection;
+
+    /// Two triangles joined by a single c-d edge
+    fn bridged_triangles() -> Vec<Reflection> {
+        vec![
+            reflection("a", &["b", "c"]),
+            reflection("d", &["e", "f"]),
+            reflection("c", &["d"]),
+        ]
+    }
+
+    fn groups(result: &CommunitiesResult) -> Vec<Vec<&str>> {
+        let mut groups: Vec<Vec<&str>> = result
+            .communities
+            .iter()
+            .map(|community| {
+                let mut members: Vec<&str> = community.members.iter().map(String::as_str).collect();
+                members.sort();
+                members
+            })
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    #[test]
+    fn test_louvain_splits_bridged_triangles() {
+        let result = compute_communities(&bridged_triangles(), &CommunitiesOptions::default());
+        assert_eq!(groups(&result), vec![vec!["a", "b", "c"], vec!["d", "e", "f"]]);
+        assert!((result.modularity - 5.0 / 14.0).abs() < 1e-12);
+
+        let c = result.membership.iter().find(|m| m.emotion_id == "c").unwrap();
+        let a = result.membership.iter().find(|m| m.emotion_id == "a").unwrap();
+        assert_eq!(c.community, a.community);
+    }
+
+    #[test]
+    fn test_label_propagation_is_deterministic() {
+        let reflections = vec![
+            reflection("a", &["b", "c"]),
+            reflection("a", &["b", "c"]),
+            reflection("d", &["e", "f"]),
+        ];
+        let options: CommunitiesOptions =
+            serde_json::from_str(r#"{"algorithm": "labelPropagation", "seed": 3}"#).unwrap();
+        let first = compute_communities(&reflections, &options);
+        let second = compute_communities(&reflections, &options);
+        assert_eq!(groups(&first), vec![vec!["a", "b", "c"], vec!["d", "e", "f"]]);
+        assert_eq!(
+            serde_json::to_string(&first).unwrap(),
+            serde_json::to_string(&second).unwrap()
+        );
+        assert_eq!(first.communities[0].id, 0);
+    }
+
+    #[test]
+    fn test_isolated_emotions_and_options() {
+        let result = compute_communities(&[reflection("a", &[])], &CommunitiesOptions::default());
+        assert_eq!(result.communities.len(), 1);
+        assert_eq!(result.modularity, 0.0);
+
+        assert!(serde_json::from_str::<CommunitiesOptions>(r#"{"resolution": 0}"#).is_err());
+        assert!(serde_json::from_str::<CommunitiesOptions>(r#"{"algorithm": "leiden"}"#).is_err());
+    }
+}
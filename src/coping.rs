@@ -0,0 +1,236 @@
+use super::Reflection;
+use super::bootstrap::{bootstrap_intervals, BootstrapOptions, ConfidenceInterval};
+use super::filter::{apply_filter, ReflectionFilter};
+use super::statistics::{mean, Interpolation};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Options for coping effectiveness computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopingEffectivenessOptions {
+    /// Settings for the bootstrap interval around each mean improvement
+    #[serde(default)]
+    pub bootstrap: BootstrapOptions,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+/// Effectiveness of one coping strategy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopingEffectiveness {
+    pub strategy: String,
+    /// Reflections using the strategy
+    pub usage_count: usize,
+    /// Reflections using the strategy that record both moods
+    pub mood_count: usize,
+    /// Mean of `moodAfter - moodBefore`, `null` without mood data
+    pub mean_improvement: Option<f64>,
+    /// Bootstrap interval for the mean, `null` with fewer than two mood samples
+    pub confidence_interval: Option<ConfidenceInterval>,
+    pub by_emotion: Vec<EmotionCopingEffectiveness>,
+}
+
+/// Strategy effectiveness within one primary emotion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmotionCopingEffectiveness {
+    pub emotion_id: String,
+    pub emotion_name: String,
+    pub usage_count: usize,
+    pub mood_count: usize,
+    pub mean_improvement: Option<f64>,
+}
+
+#[derive(Default)]
+struct UsageData {
+    usage_count: usize,
+    improvements: Vec<f64>,
+}
+
+impl UsageData {
+    fn record(&mut self, improvement: Option<f64>) {
+        self.usage_count += 1;
+        self.improvements.extend(improvement);
+    }
+
+    fn mean_improvement(&self) -> Option<f64> {
+        mean(&self.improvements)
+    }
+}
+
+#[derive(Default)]
+struct StrategyData {
+    overall: UsageData,
+    emotions: HashMap<String, (String, UsageData)>, // emotion_id -> (emotion_name, usage)
+}
+
+/// Rank coping strategies by how reliably they improved mood
+///
+/// Strategies with a confidence interval come first, highest lower bound
+/// first, so a consistent effect over many uses beats one lucky use. Next come
+/// strategies with a single mood sample, by mean improvement, then strategies
+/// without mood data. Ties are ordered by usage, then name.
+pub fn compute_coping_effectiveness(
+    reflections: &[Reflection],
+    options: &CopingEffectivenessOptions,
+) -> Vec<CopingEffectiveness> {
+    let mut strategies: HashMap<String, StrategyData> = HashMap::new();
+
+    for reflection in apply_filter(reflections, options.filter.as_ref(), None).iter() {
+        let used = match &reflection.coping_strategies {
+            Some(used) if !used.is_empty() => used,
+            _ => continue,
+        };
+        let improvement = match (reflection.mood_before, reflection.mood_after) {
+            (Some(before), Some(after)) => Some(after - before),
+            _ => None,
+        };
+        let emotion_id = reflection.emotion_id.clone().unwrap_or_else(|| "unknown".to_string());
+        let emotion_name = reflection.emotion_name.clone().unwrap_or_else(|| "Unknown".to_string());
+
+        let mut seen: Vec<&String> = Vec::with_capacity(used.len());
+        for strategy in used {
+            // Listing a strategy twice in one reflection counts once
+            if seen.contains(&strategy) {
+                continue;
+            }
+            seen.push(strategy);
+
+            let data = strategies.entry(strategy.clone()).or_default();
+            data.overall.record(improvement);
+            data.emotions
+                .entry(emotion_id.clone())
+                .or_insert_with(|| (emotion_name.clone(), UsageData::default()))
+                .1
+                .record(improvement);
+        }
+    }
+
+    let mut result: Vec<CopingEffectiveness> = strategies
+        .into_iter()
+        .map(|(strategy, data)| {
+            let mut improvements = data.overall.improvements.clone();
+            improvements.sort_by(|a, b| a.total_cmp(b));
+            let confidence_interval = (improvements.len() >= 2).then(|| {
+                bootstrap_intervals(&improvements, &[], Interpolation::Linear, &options.bootstrap).mean
+            });
+
+            let mut by_emotion: Vec<EmotionCopingEffectiveness> = data
+                .emotions
+                .into_iter()
+                .map(|(emotion_id, (emotion_name, usage))| EmotionCopingEffectiveness {
+                    emotion_id,
+                    emotion_name,
+                    usage_count: usage.usage_count,
+                    mood_count: usage.improvements.len(),
+                    mean_improvement: usage.mean_improvement(),
+                })
+                .collect();
+            by_emotion.sort_by(|a, b| {
+                b.usage_count
+                    .cmp(&a.usage_count)
+                    .then_with(|| a.emotion_id.cmp(&b.emotion_id))
+            });
+
+            CopingEffectiveness {
+                strategy,
+                usage_count: data.overall.usage_count,
+                mood_count: improvements.len(),
+                mean_improvement: data.overall.mean_improvement(),
+                confidence_interval,
+                by_emotion,
+            }
+        })
+        .collect();
+
+    result.sort_by(|a, b| {
+        let rank = |c: &CopingEffectiveness| {
+            match (c.confidence_interval, c.mean_improvement) {
+                (Some(interval), _) => (0, interval.lower),
+                (None, Some(mean)) => (1, mean),
+                (None, None) => (2, 0.0),
+            }
+        };
+        let ((a_tier, a_score), (b_tier, b_score)) = (rank(a), rank(b));
+        a_tier
+            .cmp(&b_tier)
+            .then_with(|| b_score.total_cmp(&a_score))
+            .then_with(|| b.usage_count.cmp(&a.usage_count))
+            .then_with(|| a.strategy.cmp(&b.strategy))
+    });
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reflection(emotion: &str, strategies: &[&str], moods: Option<(f64, f64)>) -> Reflection {
+        Reflection {
+            emotion_id: Some(emotion.to_string()),
+            emotion_name: Some(emotion.to_string()),
+            coping_strategies: Some(strategies.iter().map(|s| s.to_string()).collect()),
+            mood_before: moods.map(|(before, _)| before),
+            mood_after: moods.map(|(_, after)| after),
+            ..Reflection::test_default()
+        }
+    }
+
+    #[test]
+    fn test_ranking_by_mean_improvement() {
+        let reflections = vec![
+            reflection("anxiety", &["breathing", "walk"], Some((3.0, 7.0))),
+            reflection("anxiety", &["breathing"], Some((4.0, 6.0))),
+            reflection("sadness", &["walk"], Some((5.0, 4.0))),
+            reflection("sadness", &["music"], None),
+        ];
+        let result = compute_coping_effectiveness(&reflections, &CopingEffectivenessOptions::default());
+        let order: Vec<&str> = result.iter().map(|r| r.strategy.as_str()).collect();
+        assert_eq!(order, vec!["breathing", "walk", "music"]);
+
+        assert_eq!(result[0].usage_count, 2);
+        assert_eq!(result[0].mean_improvement, Some(3.0));
+        let interval = result[0].confidence_interval.unwrap();
+        assert!(interval.lower >= 2.0 && interval.upper <= 4.0);
+
+        assert_eq!(result[1].mean_improvement, Some(1.5));
+        assert_eq!(result[1].by_emotion.len(), 2);
+        assert_eq!(result[2].mean_improvement, None);
+        assert!(result[2].confidence_interval.is_none());
+    }
+
+    #[test]
+    fn test_consistent_strategy_outranks_single_lucky_use() {
+        let mut reflections = vec![reflection("anxiety", &["journaling"], Some((2.0, 7.0)))];
+        for before in 0..50 {
+            let before = f64::from(before % 5);
+            reflections.push(reflection("anxiety", &["breathing"], Some((before, before + 3.0))));
+        }
+        let result = compute_coping_effectiveness(&reflections, &CopingEffectivenessOptions::default());
+        let order: Vec<&str> = result.iter().map(|r| r.strategy.as_str()).collect();
+        assert_eq!(order, vec!["breathing", "journaling"]);
+        assert_eq!(result[1].mean_improvement, Some(5.0));
+        assert!(result[1].confidence_interval.is_none());
+    }
+
+    #[test]
+    fn test_by_emotion_and_duplicates() {
+        let reflections = vec![
+            reflection("anxiety", &["walk", "walk"], Some((3.0, 5.0))),
+            reflection("anger", &["walk"], Some((6.0, 6.0))),
+            reflection("anxiety", &["walk"], None),
+        ];
+        let result = compute_coping_effectiveness(&reflections, &CopingEffectivenessOptions::default());
+        assert_eq!(result.len(), 1);
+        let walk = &result[0];
+        assert_eq!((walk.usage_count, walk.mood_count), (3, 2));
+
+        let anxiety = &walk.by_emotion[0];
+        assert_eq!(anxiety.emotion_id, "anxiety");
+        assert_eq!((anxiety.usage_count, anxiety.mood_count), (2, 1));
+        assert_eq!(anxiety.mean_improvement, Some(2.0));
+    }
+}
@@ -0,0 +1,402 @@
+use serde::{Deserialize, Deserializer};
+
+use super::timezone::TimeZone;
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Parsed timestamp broken down into wall-clock fields
+///
+/// Every module buckets reflections from this type so that day, week and
+/// hour boundaries are computed identically everywhere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub offset: Option<i32>, // seconds east of UTC, None for floating local times
+}
+
+impl DateTime {
+    /// Parse an RFC 3339 timestamp, a date-only string, or Unix epoch milliseconds
+    ///
+    /// Accepted forms:
+    /// * `2024-01-15T10:00:00Z`, `2024-01-15t10:00:00.123+02:00`, `2024-01-15 10:00:00-0500`
+    /// * `2024-01-15T10:00` (seconds optional) and offset-less floating local times
+    /// * `2024-01-15` (midnight, floating)
+    /// * `1705312800000` (milliseconds since the epoch, UTC)
+    pub fn parse(ts: &str) -> Option<DateTime> {
+        let ts = ts.trim();
+        if is_epoch_millis(ts) {
+            let millis = ts.parse::<i64>().ok()?;
+            return Some(DateTime::from_epoch_seconds(millis.div_euclid(1000), 0));
+        }
+
+        let (date_part, time_part) = match ts.find(['T', 't', ' ']) {
+            Some(idx) => (&ts[..idx], Some(&ts[idx + 1..])),
+            None => (ts, None),
+        };
+
+        let date_parts: Vec<&str> = date_part.split('-').collect();
+        if date_parts.len() != 3 {
+            return None;
+        }
+        let year = parse_number(date_parts[0], 4, 4)? as i32;
+        let month = parse_number(date_parts[1], 1, 2)?;
+        let day = parse_number(date_parts[2], 1, 2)?;
+
+        // Validate month and day ranges
+        if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+            return None;
+        }
+
+        let (hour, minute, second, offset) = match time_part {
+            Some(time_part) => parse_time_of_day(time_part)?,
+            None => (0, 0, 0, None),
+        };
+
+        Some(DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            offset,
+        })
+    }
+
+    /// Wall-clock time at `offset` seconds east of UTC for the given instant
+    pub fn from_epoch_seconds(seconds: i64, offset: i32) -> DateTime {
+        let local = seconds + offset as i64;
+        let secs = local.rem_euclid(SECONDS_PER_DAY) as u32;
+        let (year, month, day) = civil_from_days(local.div_euclid(SECONDS_PER_DAY));
+        DateTime {
+            year,
+            month,
+            day,
+            hour: secs / 3600,
+            minute: secs % 3600 / 60,
+            second: secs % 60,
+            offset: Some(offset),
+        }
+    }
+
+    /// Seconds since the Unix epoch, treating floating times as UTC
+    pub fn epoch_seconds(&self) -> i64 {
+        self.day_number() * SECONDS_PER_DAY
+            + self.hour as i64 * 3600
+            + self.minute as i64 * 60
+            + self.second as i64
+            - self.offset.unwrap_or(0) as i64
+    }
+
+    /// Re-express in the target timezone when the timestamp carries a UTC offset.
+    /// Floating times and calls without a target keep their recorded wall clock.
+    pub fn in_timezone(self, timezone: Option<&TimeZone>) -> DateTime {
+        match (timezone, self.offset) {
+            (Some(tz), Some(_)) => tz.localize(self.epoch_seconds()),
+            _ => self,
+        }
+    }
+
+    /// Days since 1970-01-01 of the wall-clock date
+    pub fn day_number(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    /// Day of week, 0 = Sunday, 6 = Saturday
+    pub fn weekday(&self) -> u32 {
+        weekday_of(self.day_number())
+    }
+}
+
+/// Parse a timestamp and convert it to the target timezone's wall clock
+pub fn parse_timestamp(ts: &str, timezone: Option<&TimeZone>) -> Option<DateTime> {
+    DateTime::parse(ts).map(|dt| dt.in_timezone(timezone))
+}
+
+/// Deserialize a timestamp given either as a string or as epoch milliseconds
+pub fn deserialize_timestamp<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RawTimestamp {
+        Text(String),
+        Millis(i64),
+        FractionalMillis(f64),
+    }
+
+    Ok(match RawTimestamp::deserialize(deserializer) {
+        Ok(RawTimestamp::Text(text)) => text,
+        Ok(RawTimestamp::Millis(millis)) => millis.to_string(),
+        Ok(RawTimestamp::FractionalMillis(millis)) if millis.is_finite() => (millis.floor() as i64).to_string(),
+        _ => {
+            return Err(serde::de::Error::custom(
+                "expected an RFC 3339 string or epoch milliseconds",
+            ))
+        }
+    })
+}
+
+/// Parse a UTC offset designator (`Z`, `+02:00`, `-0530`, `+05`) into seconds east of UTC
+pub fn parse_utc_offset(s: &str) -> Option<i32> {
+    if s == "Z" || s == "z" {
+        return Some(0);
+    }
+
+    let sign = match s.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let digits = &s[1..];
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if digits.len() == 4 => digits.split_at(2),
+        None if digits.len() == 2 => (digits, "00"),
+        None => return None,
+    };
+
+    let hours = parse_number(hours, 2, 2)? as i32;
+    let minutes = parse_number(minutes, 2, 2)? as i32;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Parse the time portion of a timestamp (`10:00:00.000+02:00`)
+/// into hour, minute, second and the UTC offset if one is present
+fn parse_time_of_day(time_part: &str) -> Option<(u32, u32, u32, Option<i32>)> {
+    let (clock, offset) = if let Some(clock) = time_part.strip_suffix(['Z', 'z']) {
+        (clock, Some(0))
+    } else if let Some(idx) = time_part.find(['+', '-']) {
+        (&time_part[..idx], Some(parse_utc_offset(&time_part[idx..])?))
+    } else {
+        (time_part, None)
+    };
+
+    // Fractional seconds must be digits but are otherwise ignored
+    let clock = match clock.split_once('.') {
+        Some((clock, fraction)) => {
+            if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            clock
+        }
+        None => clock,
+    };
+
+    let parts: Vec<&str> = clock.split(':').collect();
+    if parts.len() < 2 || parts.len() > 3 {
+        return None;
+    }
+
+    let hour = parse_number(parts[0], 2, 2)?;
+    let minute = parse_number(parts[1], 2, 2)?;
+    let second = match parts.get(2) {
+        Some(second) => parse_number(second, 2, 2)?,
+        None => 0,
+    };
+
+    // Validate hour, minute and second ranges (60 allows a leap second)
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    Some((hour, minute, second.min(59), offset))
+}
+
+/// Parse an unsigned decimal field of `min`-`max` ASCII digits
+fn parse_number(s: &str, min: usize, max: usize) -> Option<u32> {
+    if s.len() < min || s.len() > max || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse::<u32>().ok()
+}
+
+fn is_epoch_millis(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    !digits.is_empty() && !digits.contains('-') && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Return the number of days in a given month, accounting for leap years
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 => {
+            if is_leap_year(year) { 29 } else { 28 }
+        }
+        _ => 0,
+    }
+}
+
+/// Check if a year is a leap year
+pub fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date
+pub fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year as i64 - 1 } else { year as i64 };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Proleptic Gregorian date for a count of days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i32, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+    (year, month, day)
+}
+
+/// Day of week for a day number, 0 = Sunday, 6 = Saturday
+pub fn weekday_of(days: i64) -> u32 {
+    (days + 4).rem_euclid(7) as u32 // 1970-01-01 was a Thursday
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp() {
+        let dt = DateTime::parse("2024-01-15T10:00:00Z").unwrap();
+        assert_eq!(dt.year, 2024);
+        assert_eq!(dt.month, 1);
+        assert_eq!(dt.day, 15);
+        assert_eq!(dt.hour, 10);
+        assert_eq!(dt.minute, 0);
+        assert_eq!(dt.offset, Some(0));
+    }
+
+    #[test]
+    fn test_parse_timestamp_with_fractional_seconds() {
+        let dt = DateTime::parse("2024-01-15T10:30:45.123456Z").unwrap();
+        assert_eq!(dt.hour, 10);
+        assert_eq!(dt.minute, 30);
+        assert_eq!(dt.second, 45);
+        assert!(DateTime::parse("2024-01-15T10:30:45.Z").is_none());
+        assert!(DateTime::parse("2024-01-15T10:30:45.abcZ").is_none());
+    }
+
+    #[test]
+    fn test_parse_timestamp_offsets() {
+        let dt = DateTime::parse("2024-01-15T23:30:00+02:00").unwrap();
+        assert_eq!(dt.offset, Some(7200));
+        assert_eq!(dt.hour, 23);
+        let dt = DateTime::parse("2024-01-15 08:15:00-0500").unwrap();
+        assert_eq!(dt.offset, Some(-18000));
+        let dt = DateTime::parse("2024-01-15T08:15").unwrap();
+        assert_eq!(dt.offset, None);
+        assert!(DateTime::parse("2024-01-15T08:15:00+25:00").is_none());
+    }
+
+    #[test]
+    fn test_parse_date_only() {
+        let dt = DateTime::parse("2024-01-15").unwrap();
+        assert_eq!((dt.day, dt.hour, dt.offset), (15, 0, None));
+    }
+
+    #[test]
+    fn test_parse_epoch_millis() {
+        let dt = DateTime::parse("1705312800000").unwrap();
+        assert_eq!((dt.year, dt.month, dt.day, dt.hour), (2024, 1, 15, 10));
+        assert_eq!(dt.offset, Some(0));
+        let dt = DateTime::parse("-1").unwrap();
+        assert_eq!((dt.year, dt.month, dt.day, dt.hour, dt.second), (1969, 12, 31, 23, 59));
+    }
+
+    #[test]
+    fn test_parse_timestamp_rejects_invalid_month() {
+        assert!(DateTime::parse("2024-13-15T10:00:00Z").is_none());
+        assert!(DateTime::parse("2024-00-15T10:00:00Z").is_none());
+    }
+
+    #[test]
+    fn test_parse_timestamp_rejects_invalid_day() {
+        assert!(DateTime::parse("2024-02-30T10:00:00Z").is_none());
+        assert!(DateTime::parse("2024-01-32T10:00:00Z").is_none());
+        assert!(DateTime::parse("2024-01-00T10:00:00Z").is_none());
+    }
+
+    #[test]
+    fn test_parse_timestamp_rejects_invalid_time() {
+        assert!(DateTime::parse("2024-01-15T25:00:00Z").is_none());
+        assert!(DateTime::parse("2024-01-15T10:60:00Z").is_none());
+        assert!(DateTime::parse("2024-01-15T10:00:61Z").is_none());
+        assert!(DateTime::parse("2024-01-15Tgarbage").is_none());
+        assert!(DateTime::parse("2024-01-15T").is_none());
+        assert!(DateTime::parse("not-a-date").is_none());
+    }
+
+    #[test]
+    fn test_leap_second_clamped() {
+        let dt = DateTime::parse("2016-12-31T23:59:60Z").unwrap();
+        assert_eq!(dt.second, 59);
+    }
+
+    #[test]
+    fn test_leap_year_feb_29() {
+        // 2024 is a leap year
+        assert!(DateTime::parse("2024-02-29T10:00:00Z").is_some());
+        // 2023 is not a leap year
+        assert!(DateTime::parse("2023-02-29T10:00:00Z").is_none());
+    }
+
+    #[test]
+    fn test_weekday() {
+        // January 15, 2024 is a Monday (1)
+        assert_eq!(DateTime::parse("2024-01-15").unwrap().weekday(), 1);
+        // January 14, 2024 is a Sunday (0)
+        assert_eq!(DateTime::parse("2024-01-14").unwrap().weekday(), 0);
+        // January 20, 2024 is a Saturday (6)
+        assert_eq!(DateTime::parse("2024-01-20").unwrap().weekday(), 6);
+    }
+
+    #[test]
+    fn test_epoch_round_trip() {
+        let dt = DateTime::parse("2024-02-29T23:30:00Z").unwrap();
+        assert_eq!(DateTime::from_epoch_seconds(dt.epoch_seconds(), 0), dt);
+        assert_eq!(DateTime::parse("1970-01-01T00:00:00Z").unwrap().epoch_seconds(), 0);
+    }
+
+    #[test]
+    fn test_in_timezone() {
+        let tz = TimeZone::parse("Europe/Berlin").unwrap();
+        let dt = parse_timestamp("2024-01-15T23:30:00Z", Some(&tz)).unwrap();
+        assert_eq!((dt.day, dt.hour, dt.offset), (16, 0, Some(3600)));
+        // Floating times keep their wall clock
+        let dt = parse_timestamp("2024-01-15T23:30:00", Some(&tz)).unwrap();
+        assert_eq!((dt.day, dt.hour), (15, 23));
+    }
+
+    #[test]
+    fn test_deserialize_timestamp() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            #[serde(deserialize_with = "deserialize_timestamp")]
+            timestamp: String,
+        }
+        let w: Wrapper = serde_json::from_str(r#"{"timestamp": 1705312800000}"#).unwrap();
+        assert_eq!(w.timestamp, "1705312800000");
+        let w: Wrapper = serde_json::from_str(r#"{"timestamp": "2024-01-15"}"#).unwrap();
+        assert_eq!(w.timestamp, "2024-01-15");
+        assert!(serde_json::from_str::<Wrapper>(r#"{"timestamp": true}"#).is_err());
+    }
+}
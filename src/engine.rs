@@ -0,0 +1,426 @@
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use super::envelope::{parse_input, parse_options, respond};
+use super::{
+    compute_co_occurrence, compute_co_occurrence_matrix, compute_communities,
+    compute_coping_effectiveness, compute_emotion_network, compute_location_patterns,
+    compute_mood_shifts, compute_people_impact, compute_time_patterns, compute_trends, CoOccurrence,
+    CoOccurrenceMatrix, CoOccurrenceMatrixOptions, CoOccurrenceOptions, CommunitiesOptions,
+    CommunitiesResult, CopingEffectiveness, CopingEffectivenessOptions, EmotionNetworkOptions,
+    LocationPatternsOptions, LocationPatternsResult, MoodShiftsOptions, MoodShiftsResult, NetworkExport,
+    PeopleImpactOptions, PersonImpact, Reflection, TimePatternAggregates, TimePatternsOptions,
+    TimePatternsResult, TrendAggregates, TrendsOptions, TrendsResult, EMPTY_CO_OCCURRENCE_MATRIX_JSON,
+    EMPTY_COMMUNITIES_JSON, EMPTY_LOCATION_PATTERNS_JSON, EMPTY_MOOD_SHIFTS_JSON, EMPTY_NETWORK_JSON,
+    EMPTY_TIME_PATTERNS_JSON, EMPTY_TRENDS_JSON,
+};
+
+/// Memoized query result along with the canonical options it was computed for
+type Memo<T> = Option<(String, T)>;
+
+/// Summary returned after ingesting reflections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestSummary {
+    pub added: usize,
+    pub total: usize,
+}
+
+/// Stateful analytics engine holding parsed reflections in WASM memory
+///
+/// Reflections are parsed once on ingest. Daily, weekly and monthly trend
+/// buckets and the time pattern buckets are updated as reflections are
+/// added and removed, so time pattern and trend queries without a timezone,
+/// filter or explicit range never revisit the reflections. Every other
+/// query memoizes its last result: repeating it with equivalent options
+/// (ignoring whitespace and key order) returns the memoized result, while
+/// different options or any mutation of the reflection set make the next
+/// query recompute from the held reflections.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct AnalyticsEngine {
+    reflections: Vec<Reflection>,
+    time_pattern_aggregates: TimePatternAggregates,
+    trend_aggregates: TrendAggregates,
+    time_patterns: Memo<TimePatternsResult>,
+    trends: Memo<TrendsResult>,
+    co_occurrence: Memo<Vec<CoOccurrence>>,
+    co_occurrence_matrix: Memo<CoOccurrenceMatrix>,
+    emotion_network: Memo<NetworkExport>,
+    communities: Memo<CommunitiesResult>,
+    mood_shifts: Memo<MoodShiftsResult>,
+    coping_effectiveness: Memo<Vec<CopingEffectiveness>>,
+    people_impact: Memo<Vec<PersonImpact>>,
+    location_patterns: Memo<LocationPatternsResult>,
+}
+
+#[wasm_bindgen]
+impl AnalyticsEngine {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> AnalyticsEngine {
+        AnalyticsEngine::default()
+    }
+
+    /// Parse and append a JSON array of reflections
+    ///
+    /// # Returns
+    /// JSON envelope whose data holds the number added and the new total
+    pub fn add_reflections(&mut self, reflections_json: &str) -> String {
+        let result = parse_input::<Vec<Reflection>>(reflections_json).map(|reflections| {
+            let added = reflections.len();
+            if added > 0 {
+                for reflection in &reflections {
+                    self.time_pattern_aggregates.add(reflection);
+                    self.trend_aggregates.add(reflection);
+                }
+                self.reflections.extend(reflections);
+                self.invalidate();
+            }
+            IngestSummary {
+                added,
+                total: self.reflections.len(),
+            }
+        });
+
+        respond(result, "{\"added\":0,\"total\":0}")
+    }
+
+    /// Remove every reflection with the given id, returning whether any was removed
+    pub fn remove_reflection(&mut self, id: &str) -> bool {
+        let (removed, kept): (Vec<Reflection>, Vec<Reflection>) = std::mem::take(&mut self.reflections)
+            .into_iter()
+            .partition(|reflection| reflection.id.as_deref() == Some(id));
+        self.reflections = kept;
+
+        for reflection in &removed {
+            self.time_pattern_aggregates.remove(reflection);
+            self.trend_aggregates.remove(reflection);
+        }
+        if !removed.is_empty() {
+            self.invalidate();
+        }
+        !removed.is_empty()
+    }
+
+    /// Drop all reflections, aggregates and memoized results
+    pub fn clear(&mut self) {
+        self.reflections.clear();
+        self.time_pattern_aggregates = TimePatternAggregates::default();
+        self.trend_aggregates = TrendAggregates::default();
+        self.invalidate();
+    }
+
+    /// Number of reflections currently held
+    pub fn count(&self) -> usize {
+        self.reflections.len()
+    }
+
+    /// Time patterns over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_time_patterns`.
+    pub fn time_patterns(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let aggregates = &self.time_pattern_aggregates;
+        let memo = &mut self.time_patterns;
+        let result = parse_options::<TimePatternsOptions>(options_json.as_deref()).map(|options| {
+            match aggregates.query(&options) {
+                Some(result) => Cow::Owned(result),
+                None => Cow::Borrowed(memoized(memo, options_key(options_json.as_deref()), || {
+                    compute_time_patterns(reflections, &options)
+                })),
+            }
+        });
+
+        respond(result, EMPTY_TIME_PATTERNS_JSON)
+    }
+
+    /// Trends over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_trends`.
+    pub fn trends(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let aggregates = &self.trend_aggregates;
+        let memo = &mut self.trends;
+        let result = parse_options::<TrendsOptions>(options_json.as_deref()).map(|options| {
+            match aggregates.query(&options) {
+                Some(result) => Cow::Owned(result),
+                None => Cow::Borrowed(memoized(memo, options_key(options_json.as_deref()), || {
+                    compute_trends(reflections, &options)
+                })),
+            }
+        });
+
+        respond(result, EMPTY_TRENDS_JSON)
+    }
+
+    /// Emotion co-occurrence over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_co_occurrence`.
+    pub fn co_occurrence(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let memo = &mut self.co_occurrence;
+        let result = parse_options::<CoOccurrenceOptions>(options_json.as_deref()).map(|options| {
+            memoized(memo, options_key(options_json.as_deref()), || compute_co_occurrence(reflections, &options))
+        });
+
+        respond(result, "[]")
+    }
+
+    /// Co-occurrence matrix over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_co_occurrence_matrix`.
+    pub fn co_occurrence_matrix(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let memo = &mut self.co_occurrence_matrix;
+        let result = parse_options::<CoOccurrenceMatrixOptions>(options_json.as_deref()).map(|options| {
+            memoized(memo, options_key(options_json.as_deref()), || compute_co_occurrence_matrix(reflections, &options))
+        });
+
+        respond(result, EMPTY_CO_OCCURRENCE_MATRIX_JSON)
+    }
+
+    /// Emotion network over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_emotion_network`.
+    pub fn emotion_network(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let memo = &mut self.emotion_network;
+        let result = parse_options::<EmotionNetworkOptions>(options_json.as_deref()).map(|options| {
+            memoized(memo, options_key(options_json.as_deref()), || compute_emotion_network(reflections, &options))
+        });
+
+        respond(result, EMPTY_NETWORK_JSON)
+    }
+
+    /// Emotion communities over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_communities`.
+    pub fn communities(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let memo = &mut self.communities;
+        let result = parse_options::<CommunitiesOptions>(options_json.as_deref()).map(|options| {
+            memoized(memo, options_key(options_json.as_deref()), || compute_communities(reflections, &options))
+        });
+
+        respond(result, EMPTY_COMMUNITIES_JSON)
+    }
+
+    /// Mood shifts over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_mood_shifts`.
+    pub fn mood_shifts(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let memo = &mut self.mood_shifts;
+        let result = parse_options::<MoodShiftsOptions>(options_json.as_deref()).map(|options| {
+            memoized(memo, options_key(options_json.as_deref()), || compute_mood_shifts(reflections, &options))
+        });
+
+        respond(result, EMPTY_MOOD_SHIFTS_JSON)
+    }
+
+    /// Coping strategy ranking over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_coping_effectiveness`.
+    pub fn coping_effectiveness(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let memo = &mut self.coping_effectiveness;
+        let result = parse_options::<CopingEffectivenessOptions>(options_json.as_deref()).map(|options| {
+            memoized(memo, options_key(options_json.as_deref()), || compute_coping_effectiveness(reflections, &options))
+        });
+
+        respond(result, "[]")
+    }
+
+    /// People impact over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_people_impact`.
+    pub fn people_impact(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let memo = &mut self.people_impact;
+        let result = parse_options::<PeopleImpactOptions>(options_json.as_deref()).map(|options| {
+            memoized(memo, options_key(options_json.as_deref()), || compute_people_impact(reflections, &options))
+        });
+
+        respond(result, "[]")
+    }
+
+    /// Location patterns over the held reflections, as a JSON envelope
+    ///
+    /// Accepts the same options JSON as `calculate_location_patterns`.
+    pub fn location_patterns(&mut self, options_json: Option<String>) -> String {
+        let reflections = &self.reflections;
+        let memo = &mut self.location_patterns;
+        let result = parse_options::<LocationPatternsOptions>(options_json.as_deref()).map(|options| {
+            memoized(memo, options_key(options_json.as_deref()), || compute_location_patterns(reflections, &options))
+        });
+
+        respond(result, EMPTY_LOCATION_PATTERNS_JSON)
+    }
+}
+
+impl AnalyticsEngine {
+    fn invalidate(&mut self) {
+        self.time_patterns = None;
+        self.trends = None;
+        self.co_occurrence = None;
+        self.co_occurrence_matrix = None;
+        self.emotion_network = None;
+        self.communities = None;
+        self.mood_shifts = None;
+        self.coping_effectiveness = None;
+        self.people_impact = None;
+        self.location_patterns = None;
+    }
+}
+
+/// Canonical form of an options payload, so equivalent JSON shares one memo
+///
+/// Only called after the options parsed successfully. Object keys come back
+/// sorted because `serde_json::Map` is ordered.
+fn options_key(options_json: Option<&str>) -> String {
+    match options_json {
+        Some(json) if !json.trim().is_empty() => serde_json::from_str::<serde_json::Value>(json)
+            .map(|value| value.to_string())
+            .unwrap_or_else(|_| json.to_string()),
+        _ => "{}".to_string(),
+    }
+}
+
+/// Return the memoized value when it was computed for `key`, recomputing otherwise
+fn memoized<T>(slot: &mut Memo<T>, key: String, compute: impl FnOnce() -> T) -> &T {
+    if !matches!(slot, Some((memo_key, _)) if *memo_key == key) {
+        *slot = None;
+    }
+    &slot.get_or_insert_with(|| (key, compute())).1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFLECTIONS: &str = r#"[
+        {"id": "a", "timestamp": "2024-01-15T10:00:00Z", "emotionId": "joy", "relatedEmotions": ["calm"]},
+        {"id": "b", "timestamp": "2024-01-16T20:00:00Z", "emotionId": "joy", "relatedEmotions": ["calm"]}
+    ]"#;
+
+    fn data(response: &str) -> serde_json::Value {
+        let parsed: serde_json::Value = serde_json::from_str(response).unwrap();
+        assert_eq!(parsed["ok"], true);
+        parsed["data"].clone()
+    }
+
+    #[test]
+    fn test_add_and_query() {
+        let mut engine = AnalyticsEngine::new();
+        let summary = data(&engine.add_reflections(REFLECTIONS));
+        assert_eq!(summary["added"], 2);
+        assert_eq!(summary["total"], 2);
+
+        let co_occurrence = data(&engine.co_occurrence(None));
+        assert_eq!(co_occurrence[0]["count"], 2);
+        let trends = data(&engine.trends(None));
+        assert_eq!(trends["daily"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_remove_reflection_invalidates_memo() {
+        let mut engine = AnalyticsEngine::new();
+        engine.add_reflections(REFLECTIONS);
+        assert_eq!(data(&engine.co_occurrence(None))[0]["count"], 2);
+
+        assert!(engine.remove_reflection("a"));
+        assert!(!engine.remove_reflection("missing"));
+        assert_eq!(engine.count(), 1);
+        assert_eq!(data(&engine.co_occurrence(None))[0]["count"], 1);
+    }
+
+    #[test]
+    fn test_aggregates_match_full_computation() {
+        let reflections = r#"[
+            {"id": "a", "timestamp": "2024-01-15T06:00:00Z", "emotionId": "joy", "intensity": 4},
+            {"id": "b", "timestamp": "2024-01-15T23:00:00Z", "emotionId": "calm", "intensity": 6},
+            {"id": "c", "timestamp": "2024-01-20T13:00:00+02:00", "emotionId": "joy", "intensity": 8},
+            {"id": "b", "timestamp": "2024-02-03T18:30:00Z", "emotionId": "fear"},
+            {"id": "d", "timestamp": "2024-02-04T02:00:00Z", "emotionId": "calm", "intensity": 2}
+        ]"#;
+        let remaining = r#"[
+            {"id": "a", "timestamp": "2024-01-15T06:00:00Z", "emotionId": "joy", "intensity": 4},
+            {"id": "c", "timestamp": "2024-01-20T13:00:00+02:00", "emotionId": "joy", "intensity": 8},
+            {"id": "d", "timestamp": "2024-02-04T02:00:00Z", "emotionId": "calm", "intensity": 2}
+        ]"#;
+        let mut engine = AnalyticsEngine::new();
+        engine.add_reflections(reflections);
+        assert!(engine.remove_reflection("b"));
+
+        let time_pattern_options = [
+            None,
+            Some(r#"{"includeHours": true, "topN": 1, "locale": "de"}"#),
+            Some(r#"{"timeOfDay": [{"name": "early", "start": 0, "end": 7}, {"name": "late", "start": 12, "end": 0}]}"#),
+        ];
+        for options in time_pattern_options {
+            let options = options.map(str::to_string);
+            assert_eq!(
+                data(&engine.time_patterns(options.clone())),
+                data(&crate::calculate_time_patterns(remaining, options))
+            );
+        }
+
+        let trend_options = [None, Some(r#"{"weekStart": "us", "fillGaps": true}"#)];
+        for options in trend_options {
+            let options = options.map(str::to_string);
+            assert_eq!(
+                data(&engine.trends(options.clone())),
+                data(&crate::calculate_trends(remaining, options))
+            );
+        }
+
+        for id in ["a", "c", "d"] {
+            engine.remove_reflection(id);
+        }
+        assert_eq!(data(&engine.trends(None))["monthly"], serde_json::json!([]));
+        assert_eq!(data(&engine.time_patterns(None))["month"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_trends_memo_respects_options() {
+        let mut engine = AnalyticsEngine::new();
+        engine.add_reflections(r#"[{"id": "a", "timestamp": "2024-01-16T02:00:00Z"}]"#);
+        assert_eq!(data(&engine.trends(None))["daily"][0]["date"], "2024-01-16");
+        let options = Some(r#"{"timezone": "America/New_York"}"#.to_string());
+        assert_eq!(data(&engine.trends(options))["daily"][0]["date"], "2024-01-15");
+    }
+
+    #[test]
+    fn test_memo_key_ignores_formatting() {
+        assert_eq!(options_key(None), options_key(Some("  ")));
+        assert_eq!(
+            options_key(Some(r#"{"fillGaps": true, "weekStart": "us"}"#)),
+            options_key(Some(r#"{"weekStart":"us","fillGaps":true}"#))
+        );
+
+        let mut memo: Memo<usize> = None;
+        assert_eq!(*memoized(&mut memo, options_key(Some(r#"{"a": 1, "b": 2}"#)), || 1), 1);
+        assert_eq!(*memoized(&mut memo, options_key(Some(r#"{"b":2,"a":1}"#)), || 2), 1);
+        assert_eq!(*memoized(&mut memo, options_key(None), || 3), 3);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut engine = AnalyticsEngine::new();
+        engine.add_reflections(REFLECTIONS);
+        engine.clear();
+        assert_eq!(engine.count(), 0);
+        assert_eq!(data(&engine.time_patterns(None))["dayOfWeek"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_add_reflections_invalid_json_keeps_state() {
+        let mut engine = AnalyticsEngine::new();
+        engine.add_reflections(REFLECTIONS);
+        let parsed: serde_json::Value =
+            serde_json::from_str(&engine.add_reflections("not valid json")).unwrap();
+        assert_eq!(parsed["ok"], false);
+        assert_eq!(engine.count(), 2);
+    }
+}
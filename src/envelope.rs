@@ -0,0 +1,217 @@
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Serialize;
+use std::cell::Cell;
+
+thread_local! {
+    static COMPATIBILITY_MODE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Enable or disable legacy compatibility mode
+pub fn set_compatibility_mode(enabled: bool) {
+    COMPATIBILITY_MODE.with(|mode| mode.set(enabled));
+}
+
+/// Whether legacy compatibility mode is enabled
+pub fn is_compatibility_mode() -> bool {
+    COMPATIBILITY_MODE.with(|mode| mode.get())
+}
+
+/// Category of a failed call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// Payload is not syntactically valid JSON
+    InvalidJson,
+    /// Payload is valid JSON but does not match the expected shape
+    InvalidInput,
+    /// Options argument is malformed or names an unsupported setting
+    InvalidOptions,
+    /// Result could not be serialized
+    Serialization,
+}
+
+/// Error details reported in a failed envelope
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsError {
+    pub kind: ErrorKind,
+    pub path: String, // JSON path of the offending value, "$" for the document root
+    pub message: String,
+}
+
+impl AnalyticsError {
+    pub fn new(kind: ErrorKind, path: &str, message: impl Into<String>) -> Self {
+        AnalyticsError {
+            kind,
+            path: path.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Result envelope returned by every wasm entry point
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope<T> {
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<AnalyticsError>,
+}
+
+/// Deserialize a JSON payload, recording the path of the offending field on failure
+pub fn parse_input<T: DeserializeOwned>(json: &str) -> Result<T, AnalyticsError> {
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|err| {
+        let path = json_path(&err.path().to_string());
+        let inner = err.into_inner();
+        AnalyticsError::new(error_kind(&inner), &path, inner.to_string())
+    })?;
+    deserializer
+        .end()
+        .map_err(|err| AnalyticsError::new(ErrorKind::InvalidJson, "$", err.to_string()))?;
+    Ok(value)
+}
+
+/// Deserialize an optional options payload, falling back to defaults when absent
+pub fn parse_options<T: DeserializeOwned + Default>(json: Option<&str>) -> Result<T, AnalyticsError> {
+    match json {
+        Some(json) if !json.trim().is_empty() => parse_input(json).map_err(|err| AnalyticsError {
+            kind: ErrorKind::InvalidOptions,
+            ..err
+        }),
+        _ => Ok(T::default()),
+    }
+}
+
+/// Serialize a computation result into the response string.
+///
+/// In compatibility mode, successful results are returned bare and errors
+/// collapse to `legacy_fallback`, matching the pre-envelope behavior.
+pub fn respond<T: Serialize>(result: Result<T, AnalyticsError>, legacy_fallback: &str) -> String {
+    if is_compatibility_mode() {
+        return match result {
+            Ok(data) => serde_json::to_string(&data).unwrap_or_else(|_| legacy_fallback.to_string()),
+            Err(_) => legacy_fallback.to_string(),
+        };
+    }
+
+    let envelope = match result {
+        Ok(data) => Envelope {
+            ok: true,
+            data: Some(data),
+            error: None,
+        },
+        Err(error) => Envelope {
+            ok: false,
+            data: None,
+            error: Some(error),
+        },
+    };
+
+    serde_json::to_string(&envelope).unwrap_or_else(|err| {
+        let failure: Envelope<()> = Envelope {
+            ok: false,
+            data: None,
+            error: Some(AnalyticsError::new(ErrorKind::Serialization, "$", err.to_string())),
+        };
+        serde_json::to_string(&failure).unwrap_or_default()
+    })
+}
+
+/// Like `respond`, but in compatibility mode an empty input array also
+/// returns `legacy_fallback`, as the pre-envelope exports did for `[]`.
+pub fn respond_legacy<T: Serialize>(
+    input_json: &str,
+    result: Result<T, AnalyticsError>,
+    legacy_fallback: &str,
+) -> String {
+    if is_compatibility_mode() && is_empty_array(input_json) {
+        return legacy_fallback.to_string();
+    }
+    respond(result, legacy_fallback)
+}
+
+fn is_empty_array(json: &str) -> bool {
+    serde_json::from_str::<Vec<IgnoredAny>>(json).is_ok_and(|items| items.is_empty())
+}
+
+fn error_kind(err: &serde_json::Error) -> ErrorKind {
+    match err.classify() {
+        serde_json::error::Category::Data => ErrorKind::InvalidInput,
+        _ => ErrorKind::InvalidJson,
+    }
+}
+
+/// Convert a serde_path_to_error path ("[0].timestamp", ".") to JSONPath ("$[0].timestamp", "$")
+fn json_path(path: &str) -> String {
+    if path == "." {
+        "$".to_string()
+    } else if path.starts_with('[') {
+        format!("${}", path)
+    } else {
+        format!("$.{}", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_input_reports_field_path() {
+        let err = parse_input::<Vec<crate::Reflection>>(r#"[{"timestamp": true}]"#).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidInput);
+        assert_eq!(err.path, "$[0].timestamp");
+    }
+
+    #[test]
+    fn test_parse_input_reports_syntax_error() {
+        let err = parse_input::<Vec<f64>>("[1, 2").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidJson);
+    }
+
+    #[test]
+    fn test_parse_input_rejects_trailing_characters() {
+        let err = parse_input::<Vec<f64>>("[1] x").unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidJson);
+        assert_eq!(err.path, "$");
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options: crate::TrendsOptions = parse_options(None).unwrap();
+        assert!(options.timezone.is_none());
+        let err = parse_options::<crate::TrendsOptions>(Some(r#"{"timezone": "Nowhere/City"}"#))
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidOptions);
+        assert_eq!(err.path, "$.timezone");
+    }
+
+    #[test]
+    fn test_respond_compatibility_mode() {
+        set_compatibility_mode(true);
+        let err = AnalyticsError::new(ErrorKind::InvalidJson, "$", "bad");
+        assert_eq!(respond::<Vec<f64>>(Err(err), "[]"), "[]");
+        assert_eq!(respond(Ok(vec![1.0]), "[]"), "[1.0]");
+        assert_eq!(respond_legacy(" [ ] ", Ok(vec![0.0]), "{}"), "{}");
+        assert_eq!(respond_legacy("[1]", Ok(vec![1.0]), "{}"), "[1.0]");
+        set_compatibility_mode(false);
+        assert_eq!(respond_legacy("[]", Ok(vec![0.0]), "{}"), "{\"ok\":true,\"data\":[0.0]}");
+    }
+
+    #[test]
+    fn test_respond_envelope() {
+        let ok = respond(Ok(vec![1.0]), "[]");
+        assert_eq!(ok, "{\"ok\":true,\"data\":[1.0]}");
+        let err = respond::<Vec<f64>>(
+            Err(AnalyticsError::new(ErrorKind::InvalidInput, "$[0]", "bad")),
+            "[]",
+        );
+        assert_eq!(
+            err,
+            "{\"ok\":false,\"error\":{\"kind\":\"invalid_input\",\"path\":\"$[0]\",\"message\":\"bad\"}}"
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use super::datetime::{DateTime, SECONDS_PER_DAY};
+use super::timezone::TimeZone;
+use super::Reflection;
+use serde::Deserialize;
+use std::borrow::Cow;
+
+/// Filter spec applied to reflections before any aggregation
+///
+/// Every specified criterion must match. Within a list, matching any
+/// entry is enough.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReflectionFilter {
+    /// Keep reflections whose timestamp falls in this range
+    pub date_range: Option<TimeRange>,
+    /// Keep reflections whose primary or related emotions include one of these ids
+    pub emotion_ids: Option<Vec<String>>,
+    /// Keep reflections whose intensity lies in this inclusive range
+    pub intensity: Option<ValueRange>,
+    /// Keep reflections logged at a matching place, city or country
+    pub location: Option<LocationFilter>,
+    /// Keep reflections involving one of these people (matched by id, or by name when id is missing)
+    pub person_ids: Option<Vec<String>>,
+    /// Keep reflections using one of these coping strategies
+    pub coping_strategies: Option<Vec<String>>,
+}
+
+/// Time range with both ends inclusive
+///
+/// Bounds accept any timestamp `Reflection.timestamp` accepts. Times compare
+/// to the whole second, fractions ignored on both sides, so an `end` of
+/// `10:00:00Z` includes `10:00:00.500Z`; a date-only `end` includes that
+/// whole day. Date-only and offset-less bounds are wall
+/// clock times, compared in the same timezone the aggregates bucket in;
+/// bounds with a UTC offset are instants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawTimeRange")]
+pub struct TimeRange {
+    pub start: Option<TimeBound>,
+    pub end: Option<TimeBound>,
+}
+
+/// One end of a `TimeRange`, in seconds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeBound {
+    /// Seconds since the Unix epoch
+    Instant(i64),
+    /// Wall-clock seconds since 1970-01-01T00:00 in the bucketing timezone
+    WallClock(i64),
+}
+
+#[derive(Deserialize)]
+struct RawTimeRange {
+    start: Option<String>,
+    end: Option<String>,
+}
+
+impl TryFrom<RawTimeRange> for TimeRange {
+    type Error = String;
+
+    fn try_from(raw: RawTimeRange) -> Result<Self, Self::Error> {
+        let parse = |value: &str, end: bool| {
+            let dt = DateTime::parse(value).ok_or_else(|| format!("invalid timestamp `{}`", value))?;
+            // Stored bounds are exclusive: an end covers its whole day or second
+            let shift = match (end, is_date_only(value)) {
+                (false, _) => 0,
+                (true, true) => SECONDS_PER_DAY,
+                (true, false) => 1,
+            };
+            let seconds = dt.epoch_seconds() + shift;
+            Ok::<_, String>(match dt.offset {
+                Some(_) => TimeBound::Instant(seconds),
+                None => TimeBound::WallClock(seconds),
+            })
+        };
+
+        let start = raw.start.as_deref().map(|value| parse(value, false)).transpose()?;
+        let end = raw.end.as_deref().map(|value| parse(value, true)).transpose()?;
+
+        // Bounds of different kinds only order once a timezone is known
+        match (start, end) {
+            (Some(TimeBound::Instant(start)), Some(TimeBound::Instant(end)))
+            | (Some(TimeBound::WallClock(start)), Some(TimeBound::WallClock(end)))
+                if start >= end =>
+            {
+                Err("range start must be before range end".to_string())
+            }
+            _ => Ok(TimeRange { start, end }),
+        }
+    }
+}
+
+impl TimeRange {
+    /// Whether a timestamp falls in the range when bucketing in `timezone`
+    pub fn contains(&self, dt: DateTime, timezone: Option<&TimeZone>) -> bool {
+        let instant = match (dt.offset, timezone) {
+            // Floating times are read as local time in the target timezone
+            (None, Some(tz)) => {
+                let wall = dt.epoch_seconds();
+                wall - tz.offset_at(wall - tz.offset_at(wall) as i64) as i64
+            }
+            _ => dt.epoch_seconds(),
+        };
+        let local = dt.in_timezone(timezone);
+        let wall_clock = DateTime { offset: None, ..local }.epoch_seconds();
+
+        let seconds = |bound: TimeBound| match bound {
+            TimeBound::Instant(bound) => (instant, bound),
+            TimeBound::WallClock(bound) => (wall_clock, bound),
+        };
+        self.start.map(seconds).is_none_or(|(value, start)| value >= start)
+            && self.end.map(seconds).is_none_or(|(value, end)| value < end)
+    }
+}
+
+/// Inclusive numeric range; either bound may be omitted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+pub struct ValueRange {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+impl ValueRange {
+    pub fn contains(&self, value: f64) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+}
+
+/// Location criteria, compared case-insensitively
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationFilter {
+    pub place_names: Option<Vec<String>>,
+    pub cities: Option<Vec<String>>,
+    pub countries: Option<Vec<String>>,
+}
+
+impl ReflectionFilter {
+    /// Whether a reflection satisfies every criterion, reading wall-clock
+    /// date bounds in `timezone`
+    pub fn matches(&self, reflection: &Reflection, timezone: Option<&TimeZone>) -> bool {
+        if let Some(range) = &self.date_range {
+            match DateTime::parse(&reflection.timestamp) {
+                Some(dt) if range.contains(dt, timezone) => {}
+                _ => return false,
+            }
+        }
+
+        if let Some(emotion_ids) = &self.emotion_ids {
+            let primary = reflection.emotion_id.iter();
+            let related = reflection.related_emotions.iter().flatten();
+            if !primary.chain(related).any(|id| emotion_ids.contains(id)) {
+                return false;
+            }
+        }
+
+        if let Some(range) = &self.intensity {
+            if !reflection.intensity.is_some_and(|intensity| range.contains(intensity)) {
+                return false;
+            }
+        }
+
+        if let Some(location_filter) = &self.location {
+            if !location_filter.matches(reflection) {
+                return false;
+            }
+        }
+
+        if let Some(person_ids) = &self.person_ids {
+            let people = reflection.people.iter().flatten();
+            let matched = people
+                .filter_map(|person| person.id.as_ref().or(person.name.as_ref()))
+                .any(|key| person_ids.contains(key));
+            if !matched {
+                return false;
+            }
+        }
+
+        if let Some(strategies) = &self.coping_strategies {
+            let used = reflection.coping_strategies.iter().flatten();
+            if !used.into_iter().any(|strategy| strategies.contains(strategy)) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+impl LocationFilter {
+    fn matches(&self, reflection: &Reflection) -> bool {
+        let location = match &reflection.location {
+            Some(location) => location,
+            None => return false,
+        };
+
+        field_matches(&self.place_names, &location.place_name)
+            && field_matches(&self.cities, &location.city)
+            && field_matches(&self.countries, &location.country)
+    }
+}
+
+fn field_matches(wanted: &Option<Vec<String>>, actual: &Option<String>) -> bool {
+    match wanted {
+        None => true,
+        Some(wanted) => actual
+            .as_ref()
+            .is_some_and(|actual| wanted.iter().any(|w| w.eq_ignore_ascii_case(actual))),
+    }
+}
+
+fn is_date_only(value: &str) -> bool {
+    let value = value.trim();
+    !value.contains(['T', 't', ' ']) && value.get(4..5) == Some("-")
+}
+
+/// Reflections matching the filter, borrowing the input when no filter is given
+///
+/// `timezone` must be the one the caller buckets in, so a date range and the
+/// aggregate agree on which day a reflection belongs to.
+pub fn apply_filter<'a>(
+    reflections: &'a [Reflection],
+    filter: Option<&ReflectionFilter>,
+    timezone: Option<&TimeZone>,
+) -> Cow<'a, [Reflection]> {
+    match filter {
+        Some(filter) => Cow::Owned(
+            reflections
+                .iter()
+                .filter(|reflection| filter.matches(reflection, timezone))
+                .cloned()
+                .collect(),
+        ),
+        None => Cow::Borrowed(reflections),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Location, Person};
+
+    fn reflection() -> Reflection {
+        Reflection {
+            emotion_id: Some("joy".to_string()),
+            emotion_name: Some("Joy".to_string()),
+            intensity: Some(7.0),
+            related_emotions: Some(vec!["calm".to_string()]),
+            location: Some(Location {
+                place_name: Some("Work".to_string()),
+                city: Some("Berlin".to_string()),
+                country: None,
+            }),
+            people: Some(vec![Person {
+                id: Some("p1".to_string()),
+                name: Some("Alex".to_string()),
+            }]),
+            coping_strategies: Some(vec!["walk".to_string()]),
+            ..Reflection::test_default()
+        }
+    }
+
+    fn filter(json: &str) -> ReflectionFilter {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_empty_filter_matches() {
+        assert!(ReflectionFilter::default().matches(&reflection(), None));
+    }
+
+    #[test]
+    fn test_date_range() {
+        assert!(filter(r#"{"dateRange": {"start": "2024-01-15", "end": "2024-01-15"}}"#).matches(&reflection(), None));
+        assert!(!filter(r#"{"dateRange": {"start": "2024-01-16"}}"#).matches(&reflection(), None));
+        assert!(!filter(r#"{"dateRange": {"end": "2024-01-15T09:59:59Z"}}"#).matches(&reflection(), None));
+        assert!(filter(r#"{"dateRange": {"end": "2024-01-15T10:00:00Z"}}"#).matches(&reflection(), None));
+        assert!(serde_json::from_str::<ReflectionFilter>(r#"{"dateRange": {"start": "nope"}}"#).is_err());
+    }
+
+    #[test]
+    fn test_date_range_fractional_seconds() {
+        let mut fractional = reflection();
+        fractional.timestamp = "2024-01-15T10:00:00.500Z".to_string();
+        // The end covers its whole second, fractions included
+        assert!(filter(r#"{"dateRange": {"end": "2024-01-15T10:00:00Z"}}"#).matches(&fractional, None));
+        assert!(!filter(r#"{"dateRange": {"end": "2024-01-15T09:59:59.999Z"}}"#).matches(&fractional, None));
+        // Fractional bounds are truncated like timestamps
+        assert!(filter(r#"{"dateRange": {"start": "2024-01-15T10:00:00.900Z"}}"#).matches(&fractional, None));
+        assert!(!filter(r#"{"dateRange": {"start": "2024-01-15T10:00:01.000Z"}}"#).matches(&fractional, None));
+    }
+
+    #[test]
+    fn test_date_range_in_timezone() {
+        let mut late = reflection();
+        late.timestamp = "2024-01-15T23:30:00-08:00".to_string();
+        let until_15th = filter(r#"{"dateRange": {"end": "2024-01-15"}}"#);
+        let la = TimeZone::parse("America/Los_Angeles");
+        // 07:30 UTC on the 16th, but still the 15th in Los Angeles
+        assert!(until_15th.matches(&late, la.as_ref()));
+        let tokyo = TimeZone::parse("Asia/Tokyo");
+        assert!(!until_15th.matches(&late, tokyo.as_ref()));
+
+        // 2024-01-15T10:00:00Z is 19:00 in Tokyo
+        let evening = filter(r#"{"dateRange": {"start": "2024-01-15T18:00"}}"#);
+        assert!(evening.matches(&reflection(), tokyo.as_ref()));
+        assert!(!evening.matches(&reflection(), None));
+        // Offset bounds stay instants whatever the timezone
+        let instant = filter(r#"{"dateRange": {"start": "2024-01-15T18:00:00+09:00"}}"#);
+        assert!(instant.matches(&reflection(), tokyo.as_ref()));
+        assert!(instant.matches(&reflection(), None));
+    }
+
+    #[test]
+    fn test_emotion_and_intensity() {
+        assert!(filter(r#"{"emotionIds": ["calm"]}"#).matches(&reflection(), None));
+        assert!(!filter(r#"{"emotionIds": ["anger"]}"#).matches(&reflection(), None));
+        assert!(filter(r#"{"intensity": {"min": 5, "max": 7}}"#).matches(&reflection(), None));
+        assert!(!filter(r#"{"intensity": {"max": 6.5}}"#).matches(&reflection(), None));
+    }
+
+    #[test]
+    fn test_location_people_and_strategies() {
+        assert!(filter(r#"{"location": {"placeNames": ["work"]}}"#).matches(&reflection(), None));
+        assert!(!filter(r#"{"location": {"placeNames": ["work"], "countries": ["DE"]}}"#).matches(&reflection(), None));
+        assert!(filter(r#"{"personIds": ["p1"]}"#).matches(&reflection(), None));
+        assert!(!filter(r#"{"personIds": ["Alex"]}"#).matches(&reflection(), None));
+        assert!(filter(r#"{"copingStrategies": ["walk", "music"]}"#).matches(&reflection(), None));
+        assert!(!filter(r#"{"copingStrategies": ["music"]}"#).matches(&reflection(), None));
+    }
+
+    #[test]
+    fn test_apply_filter() {
+        let reflections = vec![reflection()];
+        assert!(matches!(apply_filter(&reflections, None, None), Cow::Borrowed(_)));
+        let none = filter(r#"{"emotionIds": ["anger"]}"#);
+        assert!(apply_filter(&reflections, Some(&none), None).is_empty());
+    }
+}
@@ -0,0 +1,286 @@
+use super::envelope::{AnalyticsError, ErrorKind};
+use super::statistics::{compute_statistics, Interpolation, NonFinitePolicy, StatisticsOptions};
+use serde::{Deserialize, Serialize};
+
+/// Upper bound on the number of bins, guarding against tiny bin widths
+const MAX_BINS: usize = 10_000;
+
+/// Histogram result; bins are half-open `[lo, hi)` except the last, which includes its upper edge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistogramResult {
+    /// Bin edges, one more than the number of bins
+    pub edges: Vec<f64>,
+    pub counts: Vec<usize>,
+    /// Count divided by (binned total * bin width), so the histogram integrates to 1
+    pub densities: Vec<f64>,
+    /// Values outside explicit edges
+    pub out_of_range: usize,
+    /// Non-finite values removed by the `drop` policy
+    pub dropped_count: usize,
+}
+
+/// Options for histogram computation
+///
+/// At most one of `binWidth`, `binCount`, `edges` and `method` may be given.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(try_from = "RawHistogramOptions")]
+pub struct HistogramOptions {
+    pub binning: Binning,
+    pub non_finite: NonFinitePolicy,
+}
+
+/// How bin edges are chosen
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Binning {
+    /// Bins of this width starting at the minimum value
+    Width(f64),
+    /// This many equal-width bins spanning the data
+    Count(usize),
+    /// Caller-supplied, strictly increasing edges
+    Edges(Vec<f64>),
+    /// ceil(log2 n) + 1 bins
+    #[default]
+    Sturges,
+    /// Bin width 2 * IQR / n^(1/3), falling back to Sturges when the IQR is zero
+    FreedmanDiaconis,
+}
+
+/// Automatic binning rule
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum BinMethod {
+    Sturges,
+    FreedmanDiaconis,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawHistogramOptions {
+    bin_width: Option<f64>,
+    bin_count: Option<usize>,
+    edges: Option<Vec<f64>>,
+    method: Option<BinMethod>,
+    #[serde(default)]
+    non_finite: NonFinitePolicy,
+}
+
+impl TryFrom<RawHistogramOptions> for HistogramOptions {
+    type Error = String;
+
+    fn try_from(raw: RawHistogramOptions) -> Result<Self, Self::Error> {
+        let given = [
+            raw.bin_width.is_some(),
+            raw.bin_count.is_some(),
+            raw.edges.is_some(),
+            raw.method.is_some(),
+        ];
+        if given.iter().filter(|given| **given).count() > 1 {
+            return Err("only one of binWidth, binCount, edges and method may be given".to_string());
+        }
+        if raw.non_finite == NonFinitePolicy::Propagate {
+            return Err("nonFinite \"propagate\" is not supported for histograms".to_string());
+        }
+
+        let binning = if let Some(width) = raw.bin_width {
+            if !(width.is_finite() && width > 0.0) {
+                return Err("binWidth must be a positive number".to_string());
+            }
+            Binning::Width(width)
+        } else if let Some(count) = raw.bin_count {
+            if count == 0 || count > MAX_BINS {
+                return Err(format!("binCount must be between 1 and {}", MAX_BINS));
+            }
+            Binning::Count(count)
+        } else if let Some(edges) = raw.edges {
+            if edges.len() < 2 || edges.len() > MAX_BINS + 1 {
+                return Err(format!("edges must hold between 2 and {} values", MAX_BINS + 1));
+            }
+            if edges.windows(2).any(|pair| pair[0] >= pair[1]) {
+                return Err("edges must be strictly increasing".to_string());
+            }
+            Binning::Edges(edges)
+        } else {
+            match raw.method {
+                Some(BinMethod::FreedmanDiaconis) => Binning::FreedmanDiaconis,
+                Some(BinMethod::Sturges) | None => Binning::Sturges,
+            }
+        };
+
+        Ok(HistogramOptions {
+            binning,
+            non_finite: raw.non_finite,
+        })
+    }
+}
+
+/// Compute a histogram of `values`
+pub fn compute_histogram(
+    values: &[f64],
+    options: &HistogramOptions,
+) -> Result<HistogramResult, AnalyticsError> {
+    let stats_options = StatisticsOptions {
+        quantiles: Some(Vec::new()),
+        interpolation: Interpolation::Linear,
+        non_finite: options.non_finite,
+        ..StatisticsOptions::default()
+    };
+    let stats = compute_statistics(values, &stats_options)?;
+    let finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+
+    let edges = match &options.binning {
+        Binning::Edges(edges) => edges.clone(),
+        _ if finite.is_empty() => Vec::new(),
+        binning => uniform_edges(binning, stats.min, stats.max, stats.count, stats.iqr)?,
+    };
+    let bin_count = edges.len().saturating_sub(1);
+
+    let mut counts = vec![0usize; bin_count];
+    let mut out_of_range = 0;
+    for value in &finite {
+        match bin_index(&edges, *value) {
+            Some(index) => counts[index] += 1,
+            None => out_of_range += 1,
+        }
+    }
+
+    let binned: usize = counts.iter().sum();
+    let densities = counts
+        .iter()
+        .zip(edges.windows(2))
+        .map(|(count, pair)| {
+            if binned > 0 {
+                *count as f64 / (binned as f64 * (pair[1] - pair[0]))
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    Ok(HistogramResult {
+        edges,
+        counts,
+        densities,
+        out_of_range,
+        dropped_count: stats.dropped_count,
+    })
+}
+
+/// Equal-width edges spanning `[min, max]` for the non-explicit binning rules
+fn uniform_edges(
+    binning: &Binning,
+    min: f64,
+    max: f64,
+    count: usize,
+    iqr: f64,
+) -> Result<Vec<f64>, AnalyticsError> {
+    // A single distinct value gets one unit-wide bin centred on it, as numpy does
+    let (min, max) = if min == max { (min - 0.5, max + 0.5) } else { (min, max) };
+    let range = max - min;
+
+    let (bins, width) = match binning {
+        Binning::Width(width) => {
+            let bins = (range / width).ceil().max(1.0);
+            if bins > MAX_BINS as f64 {
+                return Err(AnalyticsError::new(
+                    ErrorKind::InvalidOptions,
+                    "$.binWidth",
+                    format!("binWidth {} would produce more than {} bins", width, MAX_BINS),
+                ));
+            }
+            (bins as usize, *width)
+        }
+        Binning::Count(bins) => (*bins, range / *bins as f64),
+        Binning::FreedmanDiaconis if iqr > 0.0 => {
+            let width = 2.0 * iqr / (count as f64).cbrt();
+            let bins = ((range / width).ceil() as usize).clamp(1, MAX_BINS);
+            (bins, range / bins as f64)
+        }
+        _ => {
+            let bins = (count as f64).log2().ceil() as usize + 1;
+            (bins, range / bins as f64)
+        }
+    };
+
+    let mut edges: Vec<f64> = (0..=bins).map(|i| min + i as f64 * width).collect();
+    // Pin the last edge so rounding never leaves the maximum outside
+    edges[bins] = match binning {
+        Binning::Width(_) => edges[bins].max(max),
+        _ => max,
+    };
+    Ok(edges)
+}
+
+/// Index of the bin holding `value`, or `None` when it lies outside the edges
+fn bin_index(edges: &[f64], value: f64) -> Option<usize> {
+    let (first, last) = (*edges.first()?, *edges.last()?);
+    if value < first || value > last {
+        return None;
+    }
+    let bins = edges.len() - 1;
+    let index = edges.partition_point(|edge| *edge <= value);
+    Some(index.saturating_sub(1).min(bins - 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(json: &str) -> HistogramOptions {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_fixed_count_and_width() {
+        let values = [1.0, 2.0, 2.0, 3.0, 4.0, 5.0];
+        let result = compute_histogram(&values, &options(r#"{"binCount": 4}"#)).unwrap();
+        assert_eq!(result.edges, vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(result.counts, vec![1, 2, 1, 2]);
+        let total: f64 = result.densities.iter().sum();
+        assert!((total - 1.0).abs() < 1e-12);
+
+        let result = compute_histogram(&values, &options(r#"{"binWidth": 3}"#)).unwrap();
+        assert_eq!(result.edges, vec![1.0, 4.0, 7.0]);
+        assert_eq!(result.counts, vec![4, 2]);
+    }
+
+    #[test]
+    fn test_explicit_edges() {
+        let values = [0.0, 1.0, 5.0, 10.0, 11.0];
+        let result = compute_histogram(&values, &options(r#"{"edges": [1, 5, 10]}"#)).unwrap();
+        assert_eq!(result.counts, vec![1, 2]);
+        assert_eq!(result.out_of_range, 2);
+        assert!(serde_json::from_str::<HistogramOptions>(r#"{"edges": [1, 1]}"#).is_err());
+    }
+
+    #[test]
+    fn test_automatic_binning() {
+        let values: Vec<f64> = (1..=16).map(f64::from).collect();
+        let result = compute_histogram(&values, &HistogramOptions::default()).unwrap();
+        // Sturges: log2(16) + 1 = 5 bins
+        assert_eq!(result.counts.len(), 5);
+        assert_eq!(result.counts.iter().sum::<usize>(), 16);
+
+        let result = compute_histogram(&values, &options(r#"{"method": "freedmanDiaconis"}"#)).unwrap();
+        // IQR 7.5, width 2 * 7.5 / 16^(1/3) ~ 5.95 over a range of 15
+        assert_eq!(result.counts.len(), 3);
+
+        let constant = compute_histogram(&[4.0, 4.0], &HistogramOptions::default()).unwrap();
+        assert_eq!(constant.edges.first(), Some(&3.5));
+        assert_eq!(constant.counts.iter().sum::<usize>(), 2);
+    }
+
+    #[test]
+    fn test_options_validation_and_non_finite() {
+        assert!(serde_json::from_str::<HistogramOptions>(r#"{"binCount": 3, "binWidth": 1}"#).is_err());
+        assert!(serde_json::from_str::<HistogramOptions>(r#"{"nonFinite": "propagate"}"#).is_err());
+        assert!(compute_histogram(&[1.0, f64::NAN], &HistogramOptions::default()).is_err());
+
+        let result = compute_histogram(&[1.0, f64::NAN, 2.0], &options(r#"{"nonFinite": "drop"}"#)).unwrap();
+        assert_eq!(result.dropped_count, 1);
+        assert_eq!(result.counts.iter().sum::<usize>(), 2);
+
+        let err = compute_histogram(&[0.0, 1e9], &options(r#"{"binWidth": 0.001}"#)).unwrap_err();
+        assert_eq!(err.path, "$.binWidth");
+    }
+}
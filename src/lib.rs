@@ -9,19 +9,49 @@ pub fn init() {
 
 mod time_patterns;
 mod co_occurrence;
+mod co_occurrence_matrix;
+mod network;
+mod communities;
 mod trends;
 mod statistics;
-
+mod envelope;
+mod engine;
+mod timezone;
+mod datetime;
+mod filter;
+mod histogram;
+mod sketch;
+mod bootstrap;
+mod random;
+mod mood_shifts;
+mod coping;
+mod people;
+mod location_patterns;
+
+use envelope::{parse_input, parse_options, respond, respond_legacy};
 use time_patterns::*;
 use co_occurrence::*;
+use co_occurrence_matrix::*;
+use network::*;
+use communities::*;
 use trends::*;
 use statistics::*;
+use histogram::*;
+use sketch::*;
+use mood_shifts::*;
+use coping::*;
+use people::*;
+use location_patterns::*;
+
+pub use engine::AnalyticsEngine;
 
 /// Reflection data structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Reflection {
-    pub timestamp: String, // ISO date string
+    pub id: Option<String>,
+    #[serde(deserialize_with = "datetime::deserialize_timestamp")]
+    pub timestamp: String, // RFC 3339 string or epoch milliseconds
     pub emotion_id: Option<String>,
     pub emotion_name: Option<String>,
     pub intensity: Option<f64>,
@@ -48,11 +78,44 @@ pub struct Person {
     pub name: Option<String>,
 }
 
+#[cfg(test)]
+impl Reflection {
+    /// Reflection at 2024-01-15T10:00:00Z with every optional field unset;
+    /// tests set the fields they need with struct update syntax
+    pub(crate) fn test_default() -> Reflection {
+        Reflection {
+            id: None,
+            timestamp: "2024-01-15T10:00:00Z".to_string(),
+            emotion_id: None,
+            emotion_name: None,
+            intensity: None,
+            related_emotions: None,
+            location: None,
+            people: None,
+            coping_strategies: None,
+            mood_before: None,
+            mood_after: None,
+        }
+    }
+}
+
+/// Test reflection with a primary emotion and its related emotions
+#[cfg(test)]
+pub(crate) fn test_reflection(emotion: &str, related: &[&str]) -> Reflection {
+    Reflection {
+        emotion_id: Some(emotion.to_string()),
+        related_emotions: Some(related.iter().map(|e| e.to_string()).collect()),
+        ..Reflection::test_default()
+    }
+}
+
 /// Time pattern result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TimePattern {
     pub period: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>, // localized period name, when a locale is requested
     pub count: usize,
     pub average_intensity: Option<f64>,
     pub top_emotions: Vec<EmotionCount>,
@@ -73,6 +136,8 @@ pub struct CoOccurrence {
     pub emotion_pair: [String; 2],
     pub count: usize,
     pub percentage: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub association: Option<AssociationMetrics>, // present when `includeAssociation` is set
 }
 
 /// Trend data point
@@ -101,98 +166,325 @@ pub struct TimePatternsResult {
     pub day_of_week: Vec<TimePattern>,
     pub time_of_day: Vec<TimePattern>,
     pub month: Vec<TimePattern>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hour_of_day: Option<Vec<TimePattern>>,
+}
+
+const EMPTY_TIME_PATTERNS_JSON: &str = "{\"dayOfWeek\":[],\"timeOfDay\":[],\"month\":[]}";
+const EMPTY_LOCATION_PATTERNS_JSON: &str = "{\"placeName\":[],\"city\":[],\"country\":[]}";
+const EMPTY_CO_OCCURRENCE_MATRIX_JSON: &str = "{\"labels\":[],\"marginalCounts\":[],\"matrix\":[]}";
+const EMPTY_NETWORK_JSON: &str = "{\"nodes\":[],\"edges\":[]}";
+const EMPTY_COMMUNITIES_JSON: &str = "{\"communities\":[],\"membership\":[],\"modularity\":0.0}";
+const EMPTY_TRENDS_JSON: &str = "{\"daily\":[],\"weekly\":[],\"monthly\":[]}";
+const EMPTY_STATISTICS_JSON: &str = "{\"mean\":0,\"median\":0,\"min\":0,\"max\":0,\"percentiles\":{}}";
+const EMPTY_MOOD_SHIFTS_JSON: &str = "{\"shifts\":[],\"overall\":{\"count\":0,\"meanDelta\":null,\"medianDelta\":null,\"improved\":0,\"worsened\":0,\"unchanged\":0,\"improvedPercentage\":0.0,\"worsenedPercentage\":0.0,\"unchangedPercentage\":0.0},\"byEmotion\":[],\"trend\":{\"daily\":[],\"weekly\":[],\"monthly\":[]}}";
+const EMPTY_SKETCH_JSON: &str = "{}";
+const EMPTY_HISTOGRAM_JSON: &str = "{\"edges\":[],\"counts\":[],\"densities\":[],\"outOfRange\":0,\"droppedCount\":0}";
+
+/// Enable or disable legacy compatibility mode
+///
+/// When enabled, every `calculate_*` function returns its bare result and
+/// falls back to hard-coded empty JSON on malformed input instead of
+/// returning a result envelope. The four original exports also return that
+/// empty JSON for an empty input array.
+#[wasm_bindgen]
+pub fn set_compatibility_mode(enabled: bool) {
+    envelope::set_compatibility_mode(enabled);
 }
 
 /// Calculate time patterns (day of week, time of day, month)
 /// 
 /// # Arguments
 /// * `reflections_json` - JSON string of Reflection array
+/// * `options_json` - Optional JSON string of TimePatternsOptions
+///   (`timezone`, `timeOfDay` ranges, `topN`, `includeHours`, `locale`, `filter`)
 /// 
 /// # Returns
-/// JSON string with dayOfWeek, timeOfDay, and month patterns
+/// JSON envelope whose data holds dayOfWeek, timeOfDay, and month patterns
 #[wasm_bindgen]
-pub fn calculate_time_patterns(reflections_json: &str) -> String {
-    let reflections: Vec<Reflection> = match serde_json::from_str(reflections_json) {
-        Ok(r) => r,
-        Err(_) => return "{\"dayOfWeek\":[],\"timeOfDay\":[],\"month\":[]}".to_string(),
-    };
-
-    if reflections.is_empty() {
-        return "{\"dayOfWeek\":[],\"timeOfDay\":[],\"month\":[]}".to_string();
-    }
+pub fn calculate_time_patterns(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<TimePatternsOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_time_patterns(&reflections, &options))
+    });
 
-    let result = compute_time_patterns(&reflections);
-    
-    serde_json::to_string(&result).unwrap_or_else(|_| "{\"dayOfWeek\":[],\"timeOfDay\":[],\"month\":[]}".to_string())
+    respond_legacy(reflections_json, result, EMPTY_TIME_PATTERNS_JSON)
+}
+
+/// Calculate location patterns (place, city, country)
+/// 
+/// # Arguments
+/// * `reflections_json` - JSON string of Reflection array
+/// * `options_json` - Optional JSON string of LocationPatternsOptions (`levels`, `topN`, `filter`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds placeName, city, and country patterns for the requested levels
+#[wasm_bindgen]
+pub fn calculate_location_patterns(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<LocationPatternsOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_location_patterns(&reflections, &options))
+    });
+
+    respond(result, EMPTY_LOCATION_PATTERNS_JSON)
 }
 
 /// Calculate emotion co-occurrence matrix
 /// 
 /// # Arguments
 /// * `reflections_json` - JSON string of Reflection array
+/// * `options_json` - Optional JSON string of CoOccurrenceOptions
+///   (`includeAssociation`, `rankBy`, `topN`, `minCount`, `minPercentage`,
+///   `countMultiplicity`, `includeSelfPairs`, `filter`)
 /// 
 /// # Returns
-/// JSON string of CoOccurrence array
+/// JSON envelope whose data holds a CoOccurrence array
 #[wasm_bindgen]
-pub fn calculate_co_occurrence(reflections_json: &str) -> String {
-    let reflections: Vec<Reflection> = match serde_json::from_str(reflections_json) {
-        Ok(r) => r,
-        Err(_) => return "[]".to_string(),
-    };
-
-    if reflections.is_empty() {
-        return "[]".to_string();
-    }
+pub fn calculate_co_occurrence(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<CoOccurrenceOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_co_occurrence(&reflections, &options))
+    });
+
+    respond_legacy(reflections_json, result, "[]")
+}
+
+/// Calculate a dense emotion co-occurrence matrix for heatmaps
+/// 
+/// # Arguments
+/// * `reflections_json` - JSON string of Reflection array
+/// * `options_json` - Optional JSON string of CoOccurrenceMatrixOptions
+///   (`value`, `normalizeRows`, `order`, `countMultiplicity`, `includeSelfPairs`, `filter`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds labels, marginalCounts, and a square matrix
+#[wasm_bindgen]
+pub fn calculate_co_occurrence_matrix(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<CoOccurrenceMatrixOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_co_occurrence_matrix(&reflections, &options))
+    });
+
+    respond(result, EMPTY_CO_OCCURRENCE_MATRIX_JSON)
+}
+
+/// Build the emotion co-occurrence network with node centralities
+/// 
+/// # Arguments
+/// * `reflections_json` - JSON string of Reflection array
+/// * `options_json` - Optional JSON string of EmotionNetworkOptions
+///   (`minCount`, `format`, `countMultiplicity`, `filter`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds nodes and edges, or a GraphML/GEXF document string
+#[wasm_bindgen]
+pub fn calculate_emotion_network(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<EmotionNetworkOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_emotion_network(&reflections, &options))
+    });
 
-    let result = compute_co_occurrence(&reflections);
-    
-    serde_json::to_string(&result).unwrap_or_else(|_| "[]".to_string())
+    respond(result, EMPTY_NETWORK_JSON)
+}
+
+/// Detect communities of emotions that tend to appear together
+/// 
+/// # Arguments
+/// * `reflections_json` - JSON string of Reflection array
+/// * `options_json` - Optional JSON string of CommunitiesOptions
+///   (`algorithm`, `resolution`, `seed`, `minCount`, `countMultiplicity`, `filter`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds communities, membership, and modularity
+#[wasm_bindgen]
+pub fn calculate_communities(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<CommunitiesOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_communities(&reflections, &options))
+    });
+
+    respond(result, EMPTY_COMMUNITIES_JSON)
 }
 
 /// Calculate trends over time (daily, weekly, monthly)
 /// 
 /// # Arguments
 /// * `reflections_json` - JSON string of Reflection array
+/// * `options_json` - Optional JSON string of TrendsOptions
+///   (`timezone`, `weekStart`, `fillGaps`, `range`, `filter`)
 /// 
 /// # Returns
-/// JSON string with daily, weekly, and monthly trends
+/// JSON envelope whose data holds daily, weekly, and monthly trends
 #[wasm_bindgen]
-pub fn calculate_trends(reflections_json: &str) -> String {
-    let reflections: Vec<Reflection> = match serde_json::from_str(reflections_json) {
-        Ok(r) => r,
-        Err(_) => return "{\"daily\":[],\"weekly\":[],\"monthly\":[]}".to_string(),
-    };
-
-    if reflections.is_empty() {
-        return "{\"daily\":[],\"weekly\":[],\"monthly\":[]}".to_string();
-    }
+pub fn calculate_trends(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<TrendsOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_trends(&reflections, &options))
+    });
+
+    respond_legacy(reflections_json, result, EMPTY_TRENDS_JSON)
+}
 
-    let result = compute_trends(&reflections);
-    
-    serde_json::to_string(&result).unwrap_or_else(|_| "{\"daily\":[],\"weekly\":[],\"monthly\":[]}".to_string())
+/// Calculate mood changes from reflections recording `moodBefore` and `moodAfter`
+/// 
+/// # Arguments
+/// * `reflections_json` - JSON string of reflection array
+/// * `options_json` - Optional JSON string of MoodShiftsOptions (`tolerance`, trend bucketing options, `filter`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds per-reflection deltas, overall and per-emotion summaries and delta trends
+#[wasm_bindgen]
+pub fn calculate_mood_shifts(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<MoodShiftsOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_mood_shifts(&reflections, &options))
+    });
+
+    respond(result, EMPTY_MOOD_SHIFTS_JSON)
+}
+
+/// Rank coping strategies by the mood improvement of the reflections using them
+/// 
+/// # Arguments
+/// * `reflections_json` - JSON string of reflection array
+/// * `options_json` - Optional JSON string of CopingEffectivenessOptions (`bootstrap`, `filter`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds per-strategy usage, mean improvement, confidence interval and emotion breakdown
+#[wasm_bindgen]
+pub fn calculate_coping_effectiveness(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<CopingEffectivenessOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_coping_effectiveness(&reflections, &options))
+    });
+
+    respond(result, "[]")
+}
+
+/// Summarise the emotions and mood changes associated with each person
+/// 
+/// # Arguments
+/// * `reflections_json` - JSON string of reflection array
+/// * `options_json` - Optional JSON string of PeopleImpactOptions (`topN`, `trendPeriod`, `timezone`, `weekStart`, `filter`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds one entry per person, most frequent first
+#[wasm_bindgen]
+pub fn calculate_people_impact(reflections_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<PeopleImpactOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<Vec<Reflection>>(reflections_json)
+            .map(|reflections| compute_people_impact(&reflections, &options))
+    });
+
+    respond(result, "[]")
 }
 
 /// Calculate statistical aggregations (mean, median, percentiles)
 /// 
 /// # Arguments
-/// * `values_json` - JSON string of number array
+/// * `values_json` - JSON string of an array of numbers or `{value, weight}` objects; `null` values are treated as NaN
+/// * `options_json` - Optional JSON string of StatisticsOptions (`quantiles`, `interpolation`, `variance`, `nonFinite`, `weightKind`)
 /// 
 /// # Returns
-/// JSON string with statistical metrics
+/// JSON envelope whose data holds statistical metrics
 #[wasm_bindgen]
-pub fn calculate_statistics(values_json: &str) -> String {
-    let values: Vec<f64> = match serde_json::from_str(values_json) {
-        Ok(v) => v,
-        Err(_) => return "{\"mean\":0,\"median\":0,\"min\":0,\"max\":0,\"percentiles\":{}}".to_string(),
-    };
-
-    if values.is_empty() {
-        return "{\"mean\":0,\"median\":0,\"min\":0,\"max\":0,\"percentiles\":{}}".to_string();
-    }
+pub fn calculate_statistics(values_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<StatisticsOptions>(options_json.as_deref()).and_then(|options| {
+        let entries = parse_input::<Vec<StatisticsInput>>(values_json)?;
+        let weighted = entries.iter().any(|entry| matches!(entry, StatisticsInput::Weighted { .. }));
+        let entries: Vec<(f64, f64)> = entries
+            .into_iter()
+            .map(|entry| match entry {
+                StatisticsInput::Value(value) => (value.unwrap_or(f64::NAN), 1.0),
+                StatisticsInput::Weighted { value, weight } => (value.unwrap_or(f64::NAN), weight),
+            })
+            .collect();
+
+        if weighted {
+            compute_weighted_statistics(&entries, &options)
+        } else {
+            let values: Vec<f64> = entries.into_iter().map(|(value, _)| value).collect();
+            compute_statistics(&values, &options)
+        }
+    });
+
+    respond_legacy(values_json, result, EMPTY_STATISTICS_JSON)
+}
 
-    let result = compute_statistics(&values);
-    
-    serde_json::to_string(&result).unwrap_or_else(|_| "{\"mean\":0,\"median\":0,\"min\":0,\"max\":0,\"percentiles\":{}}".to_string())
+/// Build a mergeable quantile sketch from a batch of values
+/// 
+/// # Arguments
+/// * `values_json` - JSON string of number array; `null` entries are treated as NaN
+/// * `options_json` - Optional JSON string of SketchOptions (`compression`, `nonFinite`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds the serialized sketch state
+#[wasm_bindgen]
+pub fn create_quantile_sketch(values_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<SketchOptions>(options_json.as_deref()).and_then(|options| {
+        let values = parse_input::<Vec<Option<f64>>>(values_json)?;
+        let values: Vec<f64> = values.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect();
+        QuantileSketch::from_values(&values, &options)
+    });
+
+    respond(result, EMPTY_SKETCH_JSON)
+}
+
+/// Merge serialized quantile sketches, e.g. from several chunks or devices
+/// 
+/// # Arguments
+/// * `sketches_json` - JSON string of an array of sketch states
+/// 
+/// # Returns
+/// JSON envelope whose data holds the merged sketch, using the first sketch's compression
+#[wasm_bindgen]
+pub fn merge_quantile_sketches(sketches_json: &str) -> String {
+    let result = parse_input::<Vec<QuantileSketch>>(sketches_json).map(|sketches| {
+        let mut sketches = sketches.into_iter();
+        let mut merged = sketches.next().unwrap_or_default();
+        for sketch in sketches {
+            merged.merge(&sketch);
+        }
+        merged
+    });
+
+    respond(result, EMPTY_SKETCH_JSON)
+}
+
+/// Calculate statistical aggregations from a quantile sketch
+/// 
+/// # Arguments
+/// * `sketch_json` - JSON string of a sketch state
+/// * `options_json` - Optional JSON string of StatisticsOptions (`quantiles`, `variance`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds statistical metrics with approximate median, percentiles, IQR and MAD
+#[wasm_bindgen]
+pub fn calculate_sketch_statistics(sketch_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<StatisticsOptions>(options_json.as_deref()).and_then(|options| {
+        parse_input::<QuantileSketch>(sketch_json).map(|sketch| sketch.statistics(&options))
+    });
+
+    respond(result, EMPTY_STATISTICS_JSON)
+}
+
+/// Calculate a histogram (bin edges, counts, densities)
+/// 
+/// # Arguments
+/// * `values_json` - JSON string of number array; `null` entries are treated as NaN
+/// * `options_json` - Optional JSON string of HistogramOptions (`binWidth`, `binCount`, `edges` or `method`, plus `nonFinite`)
+/// 
+/// # Returns
+/// JSON envelope whose data holds the histogram
+#[wasm_bindgen]
+pub fn calculate_histogram(values_json: &str, options_json: Option<String>) -> String {
+    let result = parse_options::<HistogramOptions>(options_json.as_deref()).and_then(|options| {
+        let values = parse_input::<Vec<Option<f64>>>(values_json)?;
+        let values: Vec<f64> = values.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect();
+        compute_histogram(&values, &options)
+    });
+
+    respond(result, EMPTY_HISTOGRAM_JSON)
 }
 
 #[cfg(test)]
@@ -203,6 +495,7 @@ mod tests {
     fn test_calculate_time_patterns() {
         let reflections = vec![
             Reflection {
+                id: None,
                 timestamp: "2024-01-15T10:00:00Z".to_string(),
                 emotion_id: Some("joy".to_string()),
                 emotion_name: Some("Joy".to_string()),
@@ -217,16 +510,18 @@ mod tests {
         ];
 
         let json = serde_json::to_string(&reflections).unwrap();
-        let result = calculate_time_patterns(&json);
+        let result = calculate_time_patterns(&json, None);
         let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
         
-        assert!(parsed.get("dayOfWeek").is_some());
+        assert_eq!(parsed["ok"], true);
+        assert!(parsed["data"].get("dayOfWeek").is_some());
     }
 
     #[test]
     fn test_calculate_co_occurrence() {
         let reflections = vec![
             Reflection {
+                id: None,
                 timestamp: "2024-01-15T10:00:00Z".to_string(),
                 emotion_id: Some("joy".to_string()),
                 emotion_name: Some("Joy".to_string()),
@@ -241,9 +536,11 @@ mod tests {
         ];
 
         let json = serde_json::to_string(&reflections).unwrap();
-        let result = calculate_co_occurrence(&json);
-        let parsed: Vec<CoOccurrence> = serde_json::from_str(&result)
+        let result = calculate_co_occurrence(&json, None);
+        let envelope: serde_json::Value = serde_json::from_str(&result)
             .expect("co-occurrence result should be valid JSON");
+        let parsed: Vec<CoOccurrence> = serde_json::from_value(envelope["data"].clone())
+            .expect("co-occurrence data should be a CoOccurrence array");
 
         // Should have exactly one co-occurrence pair (joy + excitement)
         assert_eq!(parsed.len(), 1, "Expected one co-occurrence pair for joy+excitement");
@@ -257,25 +554,236 @@ mod tests {
 
     #[test]
     fn test_calculate_co_occurrence_invalid_json() {
-        let result = calculate_co_occurrence("not valid json");
-        assert_eq!(result, "[]");
+        let result = calculate_co_occurrence("not valid json", None);
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["ok"], false);
+        assert_eq!(parsed["error"]["kind"], "invalid_json");
+        assert_eq!(parsed["error"]["path"], "$");
     }
 
     #[test]
-    fn test_calculate_time_patterns_invalid_json() {
-        let result = calculate_time_patterns("not valid json");
-        assert_eq!(result, "{\"dayOfWeek\":[],\"timeOfDay\":[],\"month\":[]}");
+    fn test_calculate_trends_invalid_field() {
+        let result = calculate_trends(r#"[{"timestamp":"2024-01-15T10:00:00Z","intensity":"high"}]"#, None);
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["ok"], false);
+        assert_eq!(parsed["error"]["kind"], "invalid_input");
+        assert_eq!(parsed["error"]["path"], "$[0].intensity");
     }
 
     #[test]
-    fn test_calculate_trends_invalid_json() {
-        let result = calculate_trends("not valid json");
-        assert_eq!(result, "{\"daily\":[],\"weekly\":[],\"monthly\":[]}");
+    fn test_calculate_trends_invalid_timezone() {
+        let result = calculate_trends("[]", Some(r#"{"timezone":"Nowhere/City"}"#.to_string()));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["ok"], false);
+        assert_eq!(parsed["error"]["kind"], "invalid_options");
+        assert_eq!(parsed["error"]["path"], "$.timezone");
+    }
+
+    #[test]
+    fn test_calculate_co_occurrence_with_filter() {
+        let json = r#"[
+            {"timestamp": "2024-01-15T10:00:00Z", "emotionId": "joy", "relatedEmotions": ["calm"], "intensity": 8},
+            {"timestamp": "2024-02-15T10:00:00Z", "emotionId": "joy", "relatedEmotions": ["calm"], "intensity": 2}
+        ]"#;
+        let options = r#"{"filter": {"dateRange": {"start": "2024-02-01"}}}"#;
+        let result = calculate_co_occurrence(json, Some(options.to_string()));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"][0]["count"], 1);
+        assert_eq!(parsed["data"][0]["percentage"], 100.0);
+    }
+
+    #[test]
+    fn test_calculate_mood_shifts() {
+        let json = r#"[
+            {"timestamp": "2024-01-15T10:00:00Z", "emotionId": "anxiety", "moodBefore": 3, "moodAfter": 5},
+            {"timestamp": "2024-01-16T10:00:00Z", "emotionId": "anxiety"}
+        ]"#;
+        let result = calculate_mood_shifts(json, None);
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"]["overall"]["count"], 1);
+        assert_eq!(parsed["data"]["overall"]["meanDelta"], 2.0);
+        assert_eq!(parsed["data"]["byEmotion"][0]["emotionId"], "anxiety");
+        assert_eq!(parsed["data"]["trend"]["daily"][0]["date"], "2024-01-15");
+    }
+
+    #[test]
+    fn test_calculate_coping_effectiveness() {
+        let json = r#"[
+            {"timestamp": "2024-01-15T10:00:00Z", "copingStrategies": ["walk"], "moodBefore": 3, "moodAfter": 5},
+            {"timestamp": "2024-01-16T10:00:00Z", "copingStrategies": ["walk"], "moodBefore": 4, "moodAfter": 8}
+        ]"#;
+        let options = r#"{"bootstrap": {"seed": 3, "resamples": 200}}"#.to_string();
+        let result = calculate_coping_effectiveness(json, Some(options));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"][0]["strategy"], "walk");
+        assert_eq!(parsed["data"][0]["meanImprovement"], 3.0);
+        assert!(parsed["data"][0]["confidenceInterval"]["lower"].is_number());
+    }
+
+    #[test]
+    fn test_calculate_co_occurrence_matrix() {
+        let json = r#"[
+            {"timestamp": "2024-01-15T10:00:00Z", "emotionId": "joy", "relatedEmotions": ["calm"]},
+            {"timestamp": "2024-01-16T10:00:00Z", "emotionId": "joy"}
+        ]"#;
+        let result = calculate_co_occurrence_matrix(json, Some(r#"{"normalizeRows": true}"#.to_string()));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"]["labels"], serde_json::json!(["joy", "calm"]));
+        assert_eq!(parsed["data"]["matrix"], serde_json::json!([[null, 1.0], [1.0, null]]));
+    }
+
+    #[test]
+    fn test_calculate_emotion_network() {
+        let json = r#"[{"timestamp": "2024-01-15T10:00:00Z", "emotionId": "joy", "relatedEmotions": ["calm"]}]"#;
+        let parsed: serde_json::Value =
+            serde_json::from_str(&calculate_emotion_network(json, None)).unwrap();
+        assert_eq!(parsed["data"]["nodes"].as_array().unwrap().len(), 2);
+        assert_eq!(parsed["data"]["edges"][0]["weight"], 1);
+
+        let result = calculate_emotion_network(json, Some(r#"{"format": "gexf"}"#.to_string()));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert!(parsed["data"].as_str().unwrap().starts_with("<?xml"));
+    }
+
+    #[test]
+    fn test_calculate_communities() {
+        let json = r#"[
+            {"timestamp": "2024-01-15T10:00:00Z", "emotionId": "anxious", "relatedEmotions": ["overwhelmed", "tired"]},
+            {"timestamp": "2024-01-16T10:00:00Z", "emotionId": "joy", "relatedEmotions": ["calm"]}
+        ]"#;
+        let parsed: serde_json::Value =
+            serde_json::from_str(&calculate_communities(json, None)).unwrap();
+        assert_eq!(parsed["data"]["communities"].as_array().unwrap().len(), 2);
+        assert_eq!(parsed["data"]["communities"][0]["members"].as_array().unwrap().len(), 3);
+        assert!(parsed["data"]["modularity"].as_f64().unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_calculate_location_patterns() {
+        let json = r#"[
+            {"timestamp": "2024-01-15T10:00:00Z", "emotionId": "joy", "location": {"placeName": "Home", "city": "Oslo", "country": "NO"}},
+            {"timestamp": "2024-01-16T10:00:00Z", "emotionId": "calm", "location": {"city": "Oslo", "country": "NO"}}
+        ]"#;
+        let result = calculate_location_patterns(json, Some(r#"{"levels": ["placeName", "city"]}"#.to_string()));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"]["placeName"][0]["placeName"], "Home");
+        assert_eq!(parsed["data"]["city"][0]["count"], 2);
+        assert!(parsed["data"].get("country").is_none());
+    }
+
+    #[test]
+    fn test_calculate_people_impact() {
+        let json = r#"[
+            {"timestamp": "2024-01-15T10:00:00Z", "emotionId": "joy", "people": [{"id": "p1", "name": "Alex"}]},
+            {"timestamp": "2024-01-16T10:00:00Z", "emotionId": "joy", "people": [{"id": "p1"}, {"name": "Sam"}]}
+        ]"#;
+        let result = calculate_people_impact(json, None);
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"][0]["personId"], "p1");
+        assert_eq!(parsed["data"][0]["count"], 2);
+        assert_eq!(parsed["data"][0]["topEmotions"][0]["emotionId"], "joy");
+        assert_eq!(parsed["data"][1]["personId"], "name:Sam");
+    }
+
+    #[test]
+    fn test_calculate_statistics_empty_is_ok() {
+        let result = calculate_statistics("[]", None);
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["ok"], true);
+        assert_eq!(parsed["data"]["mean"], 0.0);
+    }
+
+    #[test]
+    fn test_calculate_statistics_null_values() {
+        let result = calculate_statistics("[1, null, 3]", None);
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["ok"], false);
+        assert_eq!(parsed["error"]["path"], "$[1]");
+
+        let options = r#"{"nonFinite": "drop"}"#.to_string();
+        let result = calculate_statistics("[1, null, 3]", Some(options));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"]["mean"], 2.0);
+        assert_eq!(parsed["data"]["droppedCount"], 1);
+    }
+
+    #[test]
+    fn test_calculate_histogram() {
+        let options = r#"{"binWidth": 2}"#.to_string();
+        let result = calculate_histogram("[1, 2, 3, 4, 5]", Some(options));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"]["edges"], serde_json::json!([1.0, 3.0, 5.0]));
+        assert_eq!(parsed["data"]["counts"], serde_json::json!([2, 3]));
+
+        let result = calculate_histogram("[1]", Some(r#"{"binCount": 0}"#.to_string()));
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["error"]["kind"], "invalid_options");
+    }
+
+    #[test]
+    fn test_calculate_statistics_weighted_input() {
+        let result = calculate_statistics(r#"[{"value": 1, "weight": 3}, 5]"#, None);
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert_eq!(parsed["data"]["mean"], 3.0);
+        assert_eq!(parsed["data"]["weighted"]["mean"], 2.0);
+        assert_eq!(parsed["data"]["weighted"]["totalWeight"], 4.0);
+
+        let result = calculate_statistics("[1, 2]", None);
+        let parsed: serde_json::Value = serde_json::from_str(&result).unwrap();
+        assert!(parsed["data"].get("weighted").is_none());
+    }
+
+    #[test]
+    fn test_quantile_sketch_round_trip() {
+        let first: serde_json::Value =
+            serde_json::from_str(&create_quantile_sketch("[1, 2, 3]", None)).unwrap();
+        let second: serde_json::Value =
+            serde_json::from_str(&create_quantile_sketch("[4, 5]", None)).unwrap();
+        let sketches = serde_json::json!([first["data"], second["data"]]).to_string();
+
+        let merged: serde_json::Value =
+            serde_json::from_str(&merge_quantile_sketches(&sketches)).unwrap();
+        let stats = calculate_sketch_statistics(&merged["data"].to_string(), None);
+        let parsed: serde_json::Value = serde_json::from_str(&stats).unwrap();
+        assert_eq!(parsed["data"]["count"], 5);
+        assert_eq!(parsed["data"]["mean"], 3.0);
+        assert_eq!(parsed["data"]["median"], 3.0);
+        assert_eq!(parsed["data"]["max"], 5.0);
+    }
+
+    #[test]
+    fn test_compatibility_mode_invalid_json() {
+        set_compatibility_mode(true);
+        assert_eq!(calculate_co_occurrence("not valid json", None), "[]");
+        assert_eq!(
+            calculate_time_patterns("not valid json", None),
+            "{\"dayOfWeek\":[],\"timeOfDay\":[],\"month\":[]}"
+        );
+        assert_eq!(
+            calculate_trends("not valid json", None),
+            "{\"daily\":[],\"weekly\":[],\"monthly\":[]}"
+        );
+        let empty: serde_json::Value =
+            serde_json::to_value(compute_mood_shifts(&[], &MoodShiftsOptions::default())).unwrap();
+        let fallback: serde_json::Value =
+            serde_json::from_str(&calculate_mood_shifts("not valid json", None)).unwrap();
+        assert_eq!(fallback, empty);
+        assert_eq!(
+            calculate_statistics("not valid json", None),
+            "{\"mean\":0,\"median\":0,\"min\":0,\"max\":0,\"percentiles\":{}}"
+        );
+        set_compatibility_mode(false);
     }
 
     #[test]
-    fn test_calculate_statistics_invalid_json() {
-        let result = calculate_statistics("not valid json");
-        assert_eq!(result, "{\"mean\":0,\"median\":0,\"min\":0,\"max\":0,\"percentiles\":{}}");
+    fn test_compatibility_mode_empty_input() {
+        set_compatibility_mode(true);
+        assert_eq!(calculate_statistics("[]", None), EMPTY_STATISTICS_JSON);
+        assert_eq!(calculate_time_patterns("[]", None), EMPTY_TIME_PATTERNS_JSON);
+        assert_eq!(calculate_trends("[]", None), EMPTY_TRENDS_JSON);
+        assert_eq!(calculate_co_occurrence("[]", None), "[]");
+        let parsed: serde_json::Value = serde_json::from_str(&calculate_statistics("[2]", None)).unwrap();
+        assert_eq!(parsed["mean"], 2.0);
+        set_compatibility_mode(false);
     }
 }
@@ -0,0 +1,221 @@
+use super::{EmotionCount, Reflection};
+use super::filter::{apply_filter, ReflectionFilter};
+use super::time_patterns::{update_pattern_data, PatternData, DEFAULT_TOP_EMOTIONS};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Options for location pattern computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationPatternsOptions {
+    /// Hierarchy levels to aggregate; defaults to all three
+    pub levels: Option<Vec<LocationLevel>>,
+    /// Number of top emotions kept per location (default 5)
+    pub top_n: Option<usize>,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+/// Level of the place/city/country hierarchy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LocationLevel {
+    PlaceName,
+    City,
+    Country,
+}
+
+/// Aggregate for one location. Fields below the pattern's level are `null`,
+/// so a city pattern carries its country and a place pattern both.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationPattern {
+    pub place_name: Option<String>,
+    pub city: Option<String>,
+    pub country: Option<String>,
+    pub count: usize,
+    pub average_intensity: Option<f64>,
+    /// Reflections at this location that record both moods
+    pub mood_count: usize,
+    /// Mean of `moodAfter - moodBefore`, `null` without mood data
+    pub mean_mood_delta: Option<f64>,
+    pub top_emotions: Vec<EmotionCount>,
+}
+
+/// Location patterns result, one list per requested level
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationPatternsResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub place_name: Option<Vec<LocationPattern>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<Vec<LocationPattern>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<Vec<LocationPattern>>,
+}
+
+/// (place name, city, country)
+type LocationKey = (Option<String>, Option<String>, Option<String>);
+
+/// Compute per-place, per-city and per-country patterns from reflections
+///
+/// Places are told apart by their city and country, and cities by their
+/// country, so two "Central Park"s in different cities stay separate.
+pub fn compute_location_patterns(
+    reflections: &[Reflection],
+    options: &LocationPatternsOptions,
+) -> LocationPatternsResult {
+    let mut place_map: HashMap<LocationKey, PatternData> = HashMap::new();
+    let mut city_map: HashMap<LocationKey, PatternData> = HashMap::new();
+    let mut country_map: HashMap<LocationKey, PatternData> = HashMap::new();
+
+    for reflection in apply_filter(reflections, options.filter.as_ref(), None).iter() {
+        let location = match &reflection.location {
+            Some(location) => location,
+            None => continue,
+        };
+        let emotion_id = reflection.emotion_id.clone().unwrap_or_else(|| "unknown".to_string());
+        let emotion_name = reflection.emotion_name.clone().unwrap_or_else(|| "Unknown".to_string());
+        let mood_delta = match (reflection.mood_before, reflection.mood_after) {
+            (Some(before), Some(after)) => Some(after - before),
+            _ => None,
+        };
+
+        let update = |map: &mut HashMap<LocationKey, PatternData>, key: LocationKey| {
+            update_pattern_data(map, key, &emotion_id, &emotion_name, reflection.intensity)
+                .mood_deltas
+                .extend(mood_delta);
+        };
+
+        if location.place_name.is_some() {
+            let key = (
+                location.place_name.clone(),
+                location.city.clone(),
+                location.country.clone(),
+            );
+            update(&mut place_map, key);
+        }
+        if location.city.is_some() {
+            update(&mut city_map, (None, location.city.clone(), location.country.clone()));
+        }
+        if location.country.is_some() {
+            update(&mut country_map, (None, None, location.country.clone()));
+        }
+    }
+
+    let levels = options.levels.as_deref();
+    let wanted = |level: LocationLevel| levels.is_none_or(|levels| levels.contains(&level));
+    let top_n = options.top_n.unwrap_or(DEFAULT_TOP_EMOTIONS);
+
+    LocationPatternsResult {
+        place_name: wanted(LocationLevel::PlaceName).then(|| format_locations(place_map, top_n)),
+        city: wanted(LocationLevel::City).then(|| format_locations(city_map, top_n)),
+        country: wanted(LocationLevel::Country).then(|| format_locations(country_map, top_n)),
+    }
+}
+
+/// Most reflections first, ties ordered by country, city, then place name
+fn format_locations(map: HashMap<LocationKey, PatternData>, top_n: usize) -> Vec<LocationPattern> {
+    let mut patterns: Vec<LocationPattern> = map
+        .into_iter()
+        .map(|((place_name, city, country), data)| LocationPattern {
+            place_name,
+            city,
+            country,
+            count: data.count,
+            average_intensity: data.average_intensity(),
+            mood_count: data.mood_deltas.len(),
+            mean_mood_delta: data.mean_mood_delta(),
+            top_emotions: data.top_emotions(top_n),
+        })
+        .collect();
+
+    patterns.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.country.cmp(&b.country))
+            .then_with(|| a.city.cmp(&b.city))
+            .then_with(|| a.place_name.cmp(&b.place_name))
+    });
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Location;
+
+    fn reflection(
+        place: Option<&str>,
+        city: Option<&str>,
+        country: Option<&str>,
+        emotion: &str,
+        intensity: f64,
+        moods: Option<(f64, f64)>,
+    ) -> Reflection {
+        Reflection {
+            emotion_id: Some(emotion.to_string()),
+            emotion_name: Some(emotion.to_string()),
+            intensity: Some(intensity),
+            location: Some(Location {
+                place_name: place.map(str::to_string),
+                city: city.map(str::to_string),
+                country: country.map(str::to_string),
+            }),
+            mood_before: moods.map(|(before, _)| before),
+            mood_after: moods.map(|(_, after)| after),
+            ..Reflection::test_default()
+        }
+    }
+
+    fn sample() -> Vec<Reflection> {
+        vec![
+            reflection(Some("Office"), Some("Berlin"), Some("DE"), "stress", 8.0, Some((6.0, 4.0))),
+            reflection(Some("Office"), Some("Berlin"), Some("DE"), "stress", 6.0, None),
+            reflection(Some("Park"), Some("Berlin"), Some("DE"), "calm", 3.0, Some((4.0, 7.0))),
+            reflection(Some("Office"), Some("Paris"), Some("FR"), "joy", 5.0, None),
+            reflection(None, None, Some("FR"), "joy", 4.0, None),
+        ]
+    }
+
+    #[test]
+    fn test_hierarchy_levels() {
+        let result = compute_location_patterns(&sample(), &LocationPatternsOptions::default());
+
+        let places = result.place_name.unwrap();
+        assert_eq!(places.len(), 3);
+        let office = &places[0];
+        assert_eq!(office.place_name.as_deref(), Some("Office"));
+        assert_eq!(office.city.as_deref(), Some("Berlin"));
+        assert_eq!(office.count, 2);
+        assert_eq!(office.average_intensity, Some(7.0));
+        assert_eq!((office.mood_count, office.mean_mood_delta), (1, Some(-2.0)));
+        assert_eq!(office.top_emotions[0].emotion_id, "stress");
+
+        let cities = result.city.unwrap();
+        assert_eq!(cities[0].city.as_deref(), Some("Berlin"));
+        assert_eq!(cities[0].place_name, None);
+        assert_eq!(cities[0].count, 3);
+        assert_eq!(cities[0].mean_mood_delta, Some(0.5));
+
+        let countries = result.country.unwrap();
+        let countries: Vec<(&str, usize)> = countries
+            .iter()
+            .map(|p| (p.country.as_deref().unwrap(), p.count))
+            .collect();
+        assert_eq!(countries, vec![("DE", 3), ("FR", 2)]);
+    }
+
+    #[test]
+    fn test_requested_levels_only() {
+        let options: LocationPatternsOptions =
+            serde_json::from_str(r#"{"levels": ["city"], "topN": 1}"#).unwrap();
+        let result = compute_location_patterns(&sample(), &options);
+        assert!(result.place_name.is_none());
+        assert!(result.country.is_none());
+        let cities = result.city.unwrap();
+        assert_eq!(cities.len(), 2);
+        assert_eq!(cities[0].top_emotions.len(), 1);
+        assert!(serde_json::from_str::<LocationPatternsOptions>(r#"{"levels": ["street"]}"#).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+use super::Reflection;
+use super::datetime::parse_timestamp;
+use super::filter::{apply_filter, ReflectionFilter};
+use super::statistics::{mean, quantile, Interpolation};
+use super::timezone::TimeZone;
+use super::trends::{fill_periods, period_keys, DateRange, WeekStart};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Options for mood shift computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodShiftsOptions {
+    /// Largest absolute delta still counted as unchanged (default 0)
+    #[serde(default, deserialize_with = "deserialize_tolerance")]
+    pub tolerance: f64,
+    /// Timezone for trend buckets, as in `TrendsOptions`
+    pub timezone: Option<TimeZone>,
+    #[serde(default)]
+    pub week_start: WeekStart,
+    #[serde(default)]
+    pub fill_gaps: bool,
+    /// Reflections outside this range are ignored, as in `TrendsOptions`
+    pub range: Option<DateRange>,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+fn deserialize_tolerance<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let tolerance = f64::deserialize(deserializer)?;
+    if tolerance < 0.0 {
+        return Err(serde::de::Error::custom("tolerance must not be negative"));
+    }
+    Ok(tolerance)
+}
+
+/// Mood change recorded by one reflection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodShift {
+    pub id: Option<String>,
+    pub timestamp: String,
+    pub emotion_id: Option<String>,
+    pub mood_before: f64,
+    pub mood_after: f64,
+    pub delta: f64, // mood_after - mood_before
+}
+
+/// Aggregate of a set of mood deltas
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodShiftSummary {
+    pub count: usize,
+    pub mean_delta: Option<f64>,
+    pub median_delta: Option<f64>,
+    pub improved: usize,
+    pub worsened: usize,
+    pub unchanged: usize,
+    pub improved_percentage: f64,
+    pub worsened_percentage: f64,
+    pub unchanged_percentage: f64,
+}
+
+/// Mood shift summary for one primary emotion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmotionMoodShift {
+    pub emotion_id: String,
+    pub emotion_name: String,
+    #[serde(flatten)]
+    pub summary: MoodShiftSummary,
+}
+
+/// Mean and median delta for one trend period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodShiftTrendPoint {
+    pub date: String,
+    pub count: usize,
+    pub mean_delta: Option<f64>,
+    pub median_delta: Option<f64>,
+}
+
+/// Mood delta trends, keyed like `TrendsResult`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodShiftTrends {
+    pub daily: Vec<MoodShiftTrendPoint>,
+    pub weekly: Vec<MoodShiftTrendPoint>,
+    pub monthly: Vec<MoodShiftTrendPoint>,
+}
+
+/// Mood shift analysis result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodShiftsResult {
+    pub shifts: Vec<MoodShift>,
+    pub overall: MoodShiftSummary,
+    pub by_emotion: Vec<EmotionMoodShift>,
+    pub trend: MoodShiftTrends,
+}
+
+/// Compute mood deltas from reflections recording both `moodBefore` and `moodAfter`
+pub fn compute_mood_shifts(
+    reflections: &[Reflection],
+    options: &MoodShiftsOptions,
+) -> MoodShiftsResult {
+    let mut shifts = Vec::new();
+    let mut by_emotion: HashMap<String, (String, Vec<f64>)> = HashMap::new();
+    let mut daily_map: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut weekly_map: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut monthly_map: HashMap<String, Vec<f64>> = HashMap::new();
+    let mut extent: Option<(i64, i64)> = None;
+
+    let reflections = apply_filter(reflections, options.filter.as_ref(), options.timezone.as_ref());
+    for reflection in reflections.iter() {
+        let (mood_before, mood_after) = match (reflection.mood_before, reflection.mood_after) {
+            (Some(before), Some(after)) => (before, after),
+            _ => continue,
+        };
+        let timestamp = match parse_timestamp(&reflection.timestamp, options.timezone.as_ref()) {
+            Some(ts) => ts,
+            None => continue,
+        };
+        let day_number = timestamp.day_number();
+        if options.range.is_some_and(|range| !range.contains(day_number)) {
+            continue;
+        }
+        let delta = mood_after - mood_before;
+
+        extent = Some(match extent {
+            Some((first, last)) => (first.min(day_number), last.max(day_number)),
+            None => (day_number, day_number),
+        });
+        let (daily, weekly, monthly) = period_keys(day_number, options.week_start);
+        daily_map.entry(daily).or_default().push(delta);
+        weekly_map.entry(weekly).or_default().push(delta);
+        monthly_map.entry(monthly).or_default().push(delta);
+
+        let emotion_id = reflection.emotion_id.clone().unwrap_or_else(|| "unknown".to_string());
+        let emotion_name = reflection.emotion_name.clone().unwrap_or_else(|| "Unknown".to_string());
+        by_emotion
+            .entry(emotion_id)
+            .or_insert_with(|| (emotion_name, Vec::new()))
+            .1
+            .push(delta);
+
+        shifts.push(MoodShift {
+            id: reflection.id.clone(),
+            timestamp: reflection.timestamp.clone(),
+            emotion_id: reflection.emotion_id.clone(),
+            mood_before,
+            mood_after,
+            delta,
+        });
+    }
+
+    fill_periods(
+        [&mut daily_map, &mut weekly_map, &mut monthly_map],
+        options.range,
+        options.fill_gaps,
+        extent,
+        options.week_start,
+    );
+
+    let deltas: Vec<f64> = shifts.iter().map(|shift| shift.delta).collect();
+    let overall = summarize(&deltas, options.tolerance);
+
+    let mut by_emotion: Vec<EmotionMoodShift> = by_emotion
+        .into_iter()
+        .map(|(emotion_id, (emotion_name, deltas))| EmotionMoodShift {
+            emotion_id,
+            emotion_name,
+            summary: summarize(&deltas, options.tolerance),
+        })
+        .collect();
+    by_emotion.sort_by(|a, b| {
+        b.summary
+            .count
+            .cmp(&a.summary.count)
+            .then_with(|| a.emotion_id.cmp(&b.emotion_id))
+    });
+
+    MoodShiftsResult {
+        shifts,
+        overall,
+        by_emotion,
+        trend: MoodShiftTrends {
+            daily: format_trend(daily_map),
+            weekly: format_trend(weekly_map),
+            monthly: format_trend(monthly_map),
+        },
+    }
+}
+
+fn summarize(deltas: &[f64], tolerance: f64) -> MoodShiftSummary {
+    if deltas.is_empty() {
+        return MoodShiftSummary::default();
+    }
+
+    let count = deltas.len();
+    let improved = deltas.iter().filter(|delta| **delta > tolerance).count();
+    let worsened = deltas.iter().filter(|delta| **delta < -tolerance).count();
+    let unchanged = count - improved - worsened;
+    let percentage = |n: usize| (n as f64 / count as f64) * 100.0;
+    let (mean_delta, median_delta) = mean_and_median(deltas);
+
+    MoodShiftSummary {
+        count,
+        mean_delta,
+        median_delta,
+        improved,
+        worsened,
+        unchanged,
+        improved_percentage: percentage(improved),
+        worsened_percentage: percentage(worsened),
+        unchanged_percentage: percentage(unchanged),
+    }
+}
+
+fn mean_and_median(deltas: &[f64]) -> (Option<f64>, Option<f64>) {
+    let mut sorted = deltas.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = (!sorted.is_empty()).then(|| quantile(&sorted, 0.5, Interpolation::Linear));
+    (mean(deltas), median)
+}
+
+fn format_trend(map: HashMap<String, Vec<f64>>) -> Vec<MoodShiftTrendPoint> {
+    let mut points: Vec<MoodShiftTrendPoint> = map
+        .into_iter()
+        .map(|(date, deltas)| {
+            let (mean_delta, median_delta) = mean_and_median(&deltas);
+            MoodShiftTrendPoint {
+                date,
+                count: deltas.len(),
+                mean_delta,
+                median_delta,
+            }
+        })
+        .collect();
+    points.sort_by(|a, b| a.date.cmp(&b.date));
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reflection(
+        timestamp: &str,
+        emotion: &str,
+        before: Option<f64>,
+        after: Option<f64>,
+    ) -> Reflection {
+        Reflection {
+            timestamp: timestamp.to_string(),
+            emotion_id: Some(emotion.to_string()),
+            emotion_name: Some(emotion.to_string()),
+            mood_before: before,
+            mood_after: after,
+            ..Reflection::test_default()
+        }
+    }
+
+    fn sample() -> Vec<Reflection> {
+        vec![
+            reflection("2024-01-15T10:00:00Z", "anxiety", Some(3.0), Some(6.0)),
+            reflection("2024-01-15T18:00:00Z", "anxiety", Some(5.0), Some(6.0)),
+            reflection("2024-01-17T09:00:00Z", "sadness", Some(6.0), Some(4.0)),
+            reflection("2024-01-18T09:00:00Z", "joy", Some(7.0), Some(7.0)),
+            reflection("2024-01-18T12:00:00Z", "joy", Some(7.0), None),
+        ]
+    }
+
+    #[test]
+    fn test_overall_summary() {
+        let result = compute_mood_shifts(&sample(), &MoodShiftsOptions::default());
+        assert_eq!(result.shifts.len(), 4);
+        assert_eq!(result.shifts[0].delta, 3.0);
+
+        let overall = &result.overall;
+        assert_eq!(overall.count, 4);
+        assert_eq!(overall.mean_delta, Some(0.5));
+        assert_eq!(overall.median_delta, Some(0.5));
+        assert_eq!((overall.improved, overall.worsened, overall.unchanged), (2, 1, 1));
+        assert_eq!(overall.improved_percentage, 50.0);
+    }
+
+    #[test]
+    fn test_by_emotion_and_tolerance() {
+        let options = MoodShiftsOptions {
+            tolerance: 1.0,
+            ..MoodShiftsOptions::default()
+        };
+        let result = compute_mood_shifts(&sample(), &options);
+        let anxiety = &result.by_emotion[0];
+        assert_eq!(anxiety.emotion_id, "anxiety");
+        assert_eq!(anxiety.summary.mean_delta, Some(2.0));
+        // A delta of exactly 1 is within tolerance
+        assert_eq!((anxiety.summary.improved, anxiety.summary.unchanged), (1, 1));
+        assert!(serde_json::from_str::<MoodShiftsOptions>(r#"{"tolerance": -1}"#).is_err());
+    }
+
+    #[test]
+    fn test_trend_buckets() {
+        let options = MoodShiftsOptions {
+            fill_gaps: true,
+            ..MoodShiftsOptions::default()
+        };
+        let result = compute_mood_shifts(&sample(), &options);
+        let daily = &result.trend.daily;
+        assert_eq!(daily.len(), 4);
+        assert_eq!(daily[0].date, "2024-01-15");
+        assert_eq!(daily[0].mean_delta, Some(2.0));
+        assert_eq!(daily[1].count, 0);
+        assert_eq!(daily[1].mean_delta, None);
+        assert_eq!(result.trend.weekly[0].date, "2024-W03");
+        assert_eq!(result.trend.monthly[0].count, 4);
+    }
+
+    #[test]
+    fn test_trend_explicit_range() {
+        let options: MoodShiftsOptions =
+            serde_json::from_str(r#"{"range": {"start": "2024-01-16", "end": "2024-01-18"}}"#).unwrap();
+        let result = compute_mood_shifts(&sample(), &options);
+        let dates: Vec<&str> = result.trend.daily.iter().map(|point| point.date.as_str()).collect();
+        assert_eq!(dates, vec!["2024-01-16", "2024-01-17", "2024-01-18"]);
+        assert_eq!(result.overall.count, 2);
+    }
+}
@@ -0,0 +1,520 @@
+use super::Reflection;
+use super::co_occurrence::{count_pairs, PairCounts};
+use super::filter::{apply_filter, ReflectionFilter};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+const EIGENVECTOR_MAX_ITERATIONS: usize = 1000;
+const EIGENVECTOR_TOLERANCE: f64 = 1e-10;
+
+/// Options for emotion network computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmotionNetworkOptions {
+    /// Pairs seen in fewer reflections get no edge (default 1)
+    pub min_count: Option<usize>,
+    /// Output format (default json)
+    #[serde(default)]
+    pub format: GraphFormat,
+    /// Same as in `CoOccurrenceOptions`
+    #[serde(default)]
+    pub count_multiplicity: bool,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+/// Serialization of the network
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    #[default]
+    Json,
+    Graphml,
+    Gexf,
+}
+
+/// Emotion node with its centralities
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkNode {
+    pub id: String,
+    pub label: String,
+    /// Reflections containing the emotion
+    pub frequency: usize,
+    /// Mean intensity of reflections with this primary emotion
+    pub mean_intensity: Option<f64>,
+    /// Number of neighbours
+    pub degree: usize,
+    /// Sum of incident edge weights
+    pub weighted_degree: f64,
+    /// Normalized betweenness over shortest hop paths, in [0, 1]
+    pub betweenness: f64,
+    /// Eigenvector centrality of the weighted graph, unit Euclidean norm
+    pub eigenvector: f64,
+}
+
+/// Undirected edge weighted by co-occurrence count
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkEdge {
+    pub source: String,
+    pub target: String,
+    pub weight: usize,
+}
+
+/// Emotion co-occurrence graph
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmotionNetwork {
+    pub nodes: Vec<NetworkNode>,
+    pub edges: Vec<NetworkEdge>,
+}
+
+/// Network as a JSON graph, or a GraphML/GEXF document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum NetworkExport {
+    Graph(EmotionNetwork),
+    Document(String),
+}
+
+/// Weighted undirected graph over emotion ids, most frequent emotion first
+pub struct EmotionGraph {
+    pub labels: Vec<String>,
+    /// Per node, `(neighbour index, weight)` in index order
+    pub neighbors: Vec<Vec<(usize, f64)>>,
+}
+
+impl EmotionGraph {
+    /// Every emotion present becomes a node; pairs seen in at least `min_count`
+    /// reflections become edges weighted by their count. Self pairs are never edges.
+    pub fn from_pairs(counts: &PairCounts, min_count: usize) -> Self {
+        let mut labels: Vec<String> = counts.marginals.keys().cloned().collect();
+        labels.sort_by(|a, b| counts.marginals[b].cmp(&counts.marginals[a]).then_with(|| a.cmp(b)));
+        let index: HashMap<&str, usize> =
+            labels.iter().enumerate().map(|(i, label)| (label.as_str(), i)).collect();
+
+        let mut neighbors: Vec<Vec<(usize, f64)>> = vec![Vec::new(); labels.len()];
+        for ((first, second), &(count, support)) in &counts.pairs {
+            if first == second || support < min_count {
+                continue;
+            }
+            let (a, b) = (index[first.as_str()], index[second.as_str()]);
+            neighbors[a].push((b, count as f64));
+            neighbors[b].push((a, count as f64));
+        }
+        for adjacent in &mut neighbors {
+            adjacent.sort_by_key(|(neighbor, _)| *neighbor);
+        }
+
+        EmotionGraph { labels, neighbors }
+    }
+
+    fn len(&self) -> usize {
+        self.labels.len()
+    }
+}
+
+/// Build the emotion co-occurrence network in the requested format
+pub fn compute_emotion_network(
+    reflections: &[Reflection],
+    options: &EmotionNetworkOptions,
+) -> NetworkExport {
+    let network = build_network(reflections, options);
+    match options.format {
+        GraphFormat::Json => NetworkExport::Graph(network),
+        GraphFormat::Graphml => NetworkExport::Document(to_graphml(&network)),
+        GraphFormat::Gexf => NetworkExport::Document(to_gexf(&network)),
+    }
+}
+
+fn build_network(reflections: &[Reflection], options: &EmotionNetworkOptions) -> EmotionNetwork {
+    let reflections = apply_filter(reflections, options.filter.as_ref(), None);
+    let counts = count_pairs(&reflections, options.count_multiplicity, false);
+    let graph = EmotionGraph::from_pairs(&counts, options.min_count.unwrap_or(1));
+
+    // emotion_id -> (emotion_name, intensity sum, intensity count)
+    let mut primary: HashMap<&str, (Option<&str>, f64, usize)> = HashMap::new();
+    for reflection in reflections.iter() {
+        if let Some(emotion_id) = &reflection.emotion_id {
+            let entry = primary.entry(emotion_id).or_insert((None, 0.0, 0));
+            if entry.0.is_none() {
+                entry.0 = reflection.emotion_name.as_deref();
+            }
+            if let Some(intensity) = reflection.intensity {
+                entry.1 += intensity;
+                entry.2 += 1;
+            }
+        }
+    }
+
+    let betweenness = betweenness(&graph);
+    let eigenvector = eigenvector(&graph);
+    let nodes = graph
+        .labels
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let (name, sum, n) = primary.get(id.as_str()).copied().unwrap_or((None, 0.0, 0));
+            NetworkNode {
+                id: id.clone(),
+                label: name.unwrap_or(id).to_string(),
+                frequency: counts.marginals[id],
+                mean_intensity: (n > 0).then(|| sum / n as f64),
+                degree: graph.neighbors[i].len(),
+                weighted_degree: graph.neighbors[i].iter().map(|(_, weight)| weight).sum(),
+                betweenness: betweenness[i],
+                eigenvector: eigenvector[i],
+            }
+        })
+        .collect();
+
+    let mut edges: Vec<NetworkEdge> = graph
+        .neighbors
+        .iter()
+        .enumerate()
+        .flat_map(|(a, adjacent)| adjacent.iter().map(move |&(b, weight)| (a, b, weight)))
+        .filter(|(a, b, _)| graph.labels[*a] < graph.labels[*b])
+        .map(|(a, b, weight)| NetworkEdge {
+            source: graph.labels[a].clone(),
+            target: graph.labels[b].clone(),
+            weight: weight as usize,
+        })
+        .collect();
+    edges.sort_by(|a, b| {
+        b.weight
+            .cmp(&a.weight)
+            .then_with(|| a.source.cmp(&b.source))
+            .then_with(|| a.target.cmp(&b.target))
+    });
+
+    EmotionNetwork { nodes, edges }
+}
+
+/// Brandes' algorithm on hop distance, normalized for undirected graphs
+fn betweenness(graph: &EmotionGraph) -> Vec<f64> {
+    let n = graph.len();
+    let mut centrality = vec![0.0; n];
+
+    for source in 0..n {
+        let mut stack = Vec::with_capacity(n);
+        let mut predecessors: Vec<Vec<usize>> = vec![Vec::new(); n];
+        let mut paths = vec![0.0; n];
+        let mut distance: Vec<Option<usize>> = vec![None; n];
+        paths[source] = 1.0;
+        distance[source] = Some(0);
+
+        let mut queue = VecDeque::from([source]);
+        while let Some(v) = queue.pop_front() {
+            stack.push(v);
+            let next = distance[v].map(|d| d + 1);
+            for &(w, _) in &graph.neighbors[v] {
+                if distance[w].is_none() {
+                    distance[w] = next;
+                    queue.push_back(w);
+                }
+                if distance[w] == next {
+                    paths[w] += paths[v];
+                    predecessors[w].push(v);
+                }
+            }
+        }
+
+        let mut dependency = vec![0.0; n];
+        while let Some(w) = stack.pop() {
+            for &v in &predecessors[w] {
+                dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+            }
+            if w != source {
+                centrality[w] += dependency[w];
+            }
+        }
+    }
+
+    // Each undirected pair was counted from both ends
+    if n > 2 {
+        let scale = 1.0 / ((n - 1) * (n - 2)) as f64;
+        for value in &mut centrality {
+            *value *= scale;
+        }
+    }
+    centrality
+}
+
+/// Power iteration on A + I, which shares A's leading eigenvector but also
+/// converges on bipartite graphs. Isolated nodes score 0, and so does every
+/// node of a graph without edges.
+fn eigenvector(graph: &EmotionGraph) -> Vec<f64> {
+    let n = graph.len();
+    let connected = graph.neighbors.iter().filter(|adjacent| !adjacent.is_empty()).count();
+    if connected == 0 {
+        return vec![0.0; n];
+    }
+    // The identity term would keep an isolated node's start value, so start it at 0
+    let start = 1.0 / (connected as f64).sqrt();
+    let mut x: Vec<f64> = graph
+        .neighbors
+        .iter()
+        .map(|adjacent| if adjacent.is_empty() { 0.0 } else { start })
+        .collect();
+
+    for _ in 0..EIGENVECTOR_MAX_ITERATIONS {
+        let mut next = x.clone();
+        for (v, adjacent) in graph.neighbors.iter().enumerate() {
+            for &(w, weight) in adjacent {
+                next[v] += weight * x[w];
+            }
+        }
+        let norm = next.iter().map(|value| value * value).sum::<f64>().sqrt();
+        for value in &mut next {
+            *value /= norm;
+        }
+        let change: f64 = next.iter().zip(&x).map(|(a, b)| (a - b).abs()).sum();
+        x = next;
+        if change < n as f64 * EIGENVECTOR_TOLERANCE {
+            break;
+        }
+    }
+    x
+}
+
+/// Escape markup characters and replace characters XML 1.0 does not allow
+/// (control characters other than tab, newline and carriage return, and
+/// U+FFFE/U+FFFF) with U+FFFD
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push(char::REPLACEMENT_CHARACTER),
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Node attribute keys and GraphML types, in the order of `node_attributes`
+const NODE_ATTRIBUTE_KEYS: [(&str, &str); 6] = [
+    ("frequency", "int"),
+    ("meanIntensity", "double"),
+    ("degree", "int"),
+    ("weightedDegree", "double"),
+    ("betweenness", "double"),
+    ("eigenvector", "double"),
+];
+
+/// Node attribute values shared by both XML formats; `None` is left out
+fn node_attributes(node: &NetworkNode) -> [(&'static str, Option<String>); 6] {
+    [
+        ("frequency", Some(node.frequency.to_string())),
+        ("meanIntensity", node.mean_intensity.map(|value| value.to_string())),
+        ("degree", Some(node.degree.to_string())),
+        ("weightedDegree", Some(node.weighted_degree.to_string())),
+        ("betweenness", Some(node.betweenness.to_string())),
+        ("eigenvector", Some(node.eigenvector.to_string())),
+    ]
+}
+
+fn to_graphml(network: &EmotionNetwork) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+    xml.push_str("  <key id=\"label\" for=\"node\" attr.name=\"label\" attr.type=\"string\"/>\n");
+    for (key, kind) in NODE_ATTRIBUTE_KEYS {
+        let _ = writeln!(xml, "  <key id=\"{key}\" for=\"node\" attr.name=\"{key}\" attr.type=\"{kind}\"/>");
+    }
+    xml.push_str("  <key id=\"weight\" for=\"edge\" attr.name=\"weight\" attr.type=\"int\"/>\n");
+    xml.push_str("  <graph id=\"emotions\" edgedefault=\"undirected\">\n");
+
+    for node in &network.nodes {
+        let _ = writeln!(xml, "    <node id=\"{}\">", escape_xml(&node.id));
+        let _ = writeln!(xml, "      <data key=\"label\">{}</data>", escape_xml(&node.label));
+        for (key, value) in node_attributes(node) {
+            if let Some(value) = value {
+                let _ = writeln!(xml, "      <data key=\"{key}\">{value}</data>");
+            }
+        }
+        xml.push_str("    </node>\n");
+    }
+    for edge in &network.edges {
+        let _ = writeln!(
+            xml,
+            "    <edge source=\"{}\" target=\"{}\"><data key=\"weight\">{}</data></edge>",
+            escape_xml(&edge.source),
+            escape_xml(&edge.target),
+            edge.weight
+        );
+    }
+
+    xml.push_str("  </graph>\n</graphml>\n");
+    xml
+}
+
+fn to_gexf(network: &EmotionNetwork) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<gexf xmlns=\"http://gexf.net/1.3\" version=\"1.3\">\n");
+    xml.push_str("  <graph mode=\"static\" defaultedgetype=\"undirected\">\n");
+    xml.push_str("    <attributes class=\"node\">\n");
+    for (key, kind) in NODE_ATTRIBUTE_KEYS {
+        let kind = if kind == "int" { "integer" } else { kind };
+        let _ = writeln!(xml, "      <attribute id=\"{key}\" title=\"{key}\" type=\"{kind}\"/>");
+    }
+    xml.push_str("    </attributes>\n    <nodes>\n");
+
+    for node in &network.nodes {
+        let _ = writeln!(
+            xml,
+            "      <node id=\"{}\" label=\"{}\">",
+            escape_xml(&node.id),
+            escape_xml(&node.label)
+        );
+        xml.push_str("        <attvalues>\n");
+        for (key, value) in node_attributes(node) {
+            if let Some(value) = value {
+                let _ = writeln!(xml, "          <attvalue for=\"{key}\" value=\"{value}\"/>");
+            }
+        }
+        xml.push_str("        </attvalues>\n      </node>\n");
+    }
+    xml.push_str("    </nodes>\n    <edges>\n");
+    for (i, edge) in network.edges.iter().enumerate() {
+        let _ = writeln!(
+            xml,
+            "      <edge id=\"{}\" source=\"{}\" target=\"{}\" weight=\"{}\"/>",
+            i,
+            escape_xml(&edge.source),
+            escape_xml(&edge.target),
+            edge.weight
+        );
+    }
+
+    xml.push_str("    </edges>\n  </graph>\n</gexf>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reflection(emotion: &str, related: &[&str], intensity: f64) -> Reflection {
+        Reflection {
+            emotion_name: Some(emotion.to_uppercase()),
+            intensity: Some(intensity),
+            ..crate::test_reflection(emotion, related)
+        }
+    }
+
+    /// Star around "hub" with one heavier spoke
+    fn star() -> Vec<Reflection> {
+        vec![
+            reflection("hub", &["a"], 4.0),
+            reflection("hub", &["a"], 6.0),
+            reflection("hub", &["b"], 5.0),
+            reflection("hub", &["c"], 5.0),
+        ]
+    }
+
+    fn node<'a>(network: &'a EmotionNetwork, id: &str) -> &'a NetworkNode {
+        network.nodes.iter().find(|node| node.id == id).unwrap()
+    }
+
+    #[test]
+    fn test_nodes_edges_and_centralities() {
+        let network = build_network(&star(), &EmotionNetworkOptions::default());
+        assert_eq!(network.nodes.len(), 4);
+        assert_eq!(network.edges.len(), 3);
+        assert_eq!(network.edges[0].weight, 2);
+        assert_eq!(network.edges[0].source, "a");
+
+        let hub = node(&network, "hub");
+        assert_eq!(hub.label, "HUB");
+        assert_eq!(hub.frequency, 4);
+        assert_eq!(hub.mean_intensity, Some(5.0));
+        assert_eq!((hub.degree, hub.weighted_degree), (3, 4.0));
+        assert!((hub.betweenness - 1.0).abs() < 1e-12);
+
+        let a = node(&network, "a");
+        assert_eq!(a.label, "a");
+        assert_eq!(a.mean_intensity, None);
+        assert_eq!(a.betweenness, 0.0);
+        assert!(hub.eigenvector > a.eigenvector);
+        assert!(a.eigenvector > node(&network, "b").eigenvector);
+
+        let norm: f64 = network.nodes.iter().map(|n| n.eigenvector * n.eigenvector).sum();
+        assert!((norm - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_betweenness_on_path() {
+        let reflections = vec![
+            reflection("a", &["b"], 1.0),
+            reflection("b", &["c"], 1.0),
+            reflection("c", &["d"], 1.0),
+        ];
+        let network = build_network(&reflections, &EmotionNetworkOptions::default());
+        // b and c each lie on 2 of the 3 pairs' shortest paths that avoid them
+        assert!((node(&network, "b").betweenness - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(node(&network, "a").betweenness, 0.0);
+    }
+
+    #[test]
+    fn test_min_count_and_xml_formats() {
+        let options: EmotionNetworkOptions = serde_json::from_str(r#"{"minCount": 2}"#).unwrap();
+        let network = build_network(&star(), &options);
+        assert_eq!(network.edges.len(), 1);
+        assert_eq!(node(&network, "b").degree, 0);
+
+        let graphml: EmotionNetworkOptions = serde_json::from_str(r#"{"format": "graphml"}"#).unwrap();
+        match compute_emotion_network(&[reflection("a&b", &["c"], 1.0)], &graphml) {
+            NetworkExport::Document(xml) => {
+                assert!(xml.contains("<graphml"));
+                assert!(xml.contains("<node id=\"a&amp;b\">"));
+                assert!(xml.contains("<data key=\"weight\">1</data>"));
+            }
+            NetworkExport::Graph(_) => panic!("expected a GraphML document"),
+        }
+
+        let gexf: EmotionNetworkOptions = serde_json::from_str(r#"{"format": "gexf"}"#).unwrap();
+        match compute_emotion_network(&star(), &gexf) {
+            NetworkExport::Document(xml) => {
+                assert!(xml.contains("<gexf xmlns=\"http://gexf.net/1.3\""));
+                assert!(xml.contains("<node id=\"hub\" label=\"HUB\">"));
+                assert!(xml.contains("<attvalue for=\"degree\" value=\"3\"/>"));
+            }
+            NetworkExport::Graph(_) => panic!("expected a GEXF document"),
+        }
+        assert!(serde_json::from_str::<EmotionNetworkOptions>(r#"{"format": "dot"}"#).is_err());
+    }
+
+    #[test]
+    fn test_eigenvector_without_edges() {
+        let options: EmotionNetworkOptions = serde_json::from_str(r#"{"minCount": 5}"#).unwrap();
+        let network = build_network(&star(), &options);
+        assert!(network.edges.is_empty());
+        assert!(network.nodes.iter().all(|node| node.eigenvector == 0.0));
+
+        let options: EmotionNetworkOptions = serde_json::from_str(r#"{"minCount": 2}"#).unwrap();
+        let network = build_network(&star(), &options);
+        assert_eq!(node(&network, "b").eigenvector, 0.0);
+        assert!(node(&network, "hub").eigenvector > 0.0);
+    }
+
+    #[test]
+    fn test_escape_xml_replaces_illegal_characters() {
+        assert_eq!(escape_xml("a\u{1}b\tc\u{ffff}<"), "a\u{fffd}b\tc\u{fffd}&lt;");
+
+        let gexf: EmotionNetworkOptions = serde_json::from_str(r#"{"format": "gexf"}"#).unwrap();
+        match compute_emotion_network(&[reflection("bad\u{1}id", &["c"], 1.0)], &gexf) {
+            NetworkExport::Document(xml) => {
+                assert!(!xml.contains('\u{1}'));
+                assert!(xml.contains("<node id=\"bad\u{fffd}id\""));
+            }
+            NetworkExport::Graph(_) => panic!("expected a GEXF document"),
+        }
+    }
+}
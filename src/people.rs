@@ -0,0 +1,236 @@
+use super::{EmotionCount, Reflection, TrendDataPoint};
+use super::datetime::parse_timestamp;
+use super::filter::{apply_filter, ReflectionFilter};
+use super::time_patterns::{update_pattern_data, PatternData, DEFAULT_TOP_EMOTIONS};
+use super::timezone::TimeZone;
+use super::trends::{format_trends, period_keys, update_trend_data, TrendData, WeekStart};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Options for people impact computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeopleImpactOptions {
+    /// Number of top emotions kept per person (default 5)
+    pub top_n: Option<usize>,
+    /// Granularity of each person's trend
+    #[serde(default)]
+    pub trend_period: TrendPeriod,
+    /// Timezone for trend buckets, as in `TrendsOptions`
+    pub timezone: Option<TimeZone>,
+    #[serde(default)]
+    pub week_start: WeekStart,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+/// Trend bucket size
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TrendPeriod {
+    Daily,
+    Weekly,
+    #[default]
+    Monthly,
+}
+
+/// Emotional impact associated with one person
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonImpact {
+    /// `Person.id`, or `name:` and the name for people recorded without an id
+    pub person_id: String,
+    pub name: Option<String>,
+    pub count: usize,
+    pub average_intensity: Option<f64>,
+    /// Reflections with this person that record both moods
+    pub mood_count: usize,
+    /// Mean of `moodAfter - moodBefore`, `null` without mood data
+    pub mean_mood_delta: Option<f64>,
+    pub top_emotions: Vec<EmotionCount>,
+    pub trend: Vec<TrendDataPoint>,
+}
+
+/// Per-person details beyond the shared `PatternData` aggregate
+#[derive(Default)]
+struct PersonData {
+    name: Option<String>,
+    trend: HashMap<String, TrendData>,
+}
+
+/// Group reflections by the people involved, most frequent first
+pub fn compute_people_impact(
+    reflections: &[Reflection],
+    options: &PeopleImpactOptions,
+) -> Vec<PersonImpact> {
+    let mut patterns: HashMap<String, PatternData> = HashMap::new();
+    let mut people: HashMap<String, PersonData> = HashMap::new();
+
+    let reflections = apply_filter(reflections, options.filter.as_ref(), options.timezone.as_ref());
+    for reflection in reflections.iter() {
+        let involved = match &reflection.people {
+            Some(involved) if !involved.is_empty() => involved,
+            _ => continue,
+        };
+        let timestamp = match parse_timestamp(&reflection.timestamp, options.timezone.as_ref()) {
+            Some(ts) => ts,
+            None => continue,
+        };
+
+        let (daily, weekly, monthly) = period_keys(timestamp.day_number(), options.week_start);
+        let period = match options.trend_period {
+            TrendPeriod::Daily => daily,
+            TrendPeriod::Weekly => weekly,
+            TrendPeriod::Monthly => monthly,
+        };
+        let emotion_id = reflection.emotion_id.clone().unwrap_or_else(|| "unknown".to_string());
+        let emotion_name = reflection.emotion_name.clone().unwrap_or_else(|| "Unknown".to_string());
+        let mood_delta = match (reflection.mood_before, reflection.mood_after) {
+            (Some(before), Some(after)) => Some(after - before),
+            _ => None,
+        };
+
+        let mut seen: Vec<String> = Vec::with_capacity(involved.len());
+        for person in involved {
+            // Names live in their own namespace so an id never merges with a name-only person
+            let key = match (&person.id, &person.name) {
+                (Some(id), _) => id.clone(),
+                (None, Some(name)) => format!("name:{}", name),
+                (None, None) => continue,
+            };
+            // The same person listed twice in one reflection counts once
+            if seen.contains(&key) {
+                continue;
+            }
+
+            let intensity = reflection.intensity;
+            update_pattern_data(&mut patterns, key.clone(), &emotion_id, &emotion_name, intensity)
+                .mood_deltas
+                .extend(mood_delta);
+            let data = people.entry(key.clone()).or_default();
+            if data.name.is_none() {
+                data.name = person.name.clone();
+            }
+            update_trend_data(
+                &mut data.trend,
+                &period,
+                &emotion_id,
+                &emotion_name,
+                intensity,
+            );
+            seen.push(key);
+        }
+    }
+
+    let top_n = options.top_n.unwrap_or(DEFAULT_TOP_EMOTIONS);
+    let mut result: Vec<PersonImpact> = patterns
+        .into_iter()
+        .map(|(person_id, pattern)| {
+            let data = people.remove(&person_id).unwrap_or_default();
+            PersonImpact {
+                person_id,
+                name: data.name,
+                count: pattern.count,
+                average_intensity: pattern.average_intensity(),
+                mood_count: pattern.mood_deltas.len(),
+                mean_mood_delta: pattern.mean_mood_delta(),
+                top_emotions: pattern.top_emotions(top_n),
+                trend: format_trends(data.trend),
+            }
+        })
+        .collect();
+
+    result.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.person_id.cmp(&b.person_id)));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Person;
+
+    fn reflection(
+        timestamp: &str,
+        emotion: &str,
+        people: Vec<Person>,
+        intensity: f64,
+        moods: Option<(f64, f64)>,
+    ) -> Reflection {
+        Reflection {
+            timestamp: timestamp.to_string(),
+            emotion_id: Some(emotion.to_string()),
+            emotion_name: Some(emotion.to_string()),
+            intensity: Some(intensity),
+            people: Some(people),
+            mood_before: moods.map(|(before, _)| before),
+            mood_after: moods.map(|(_, after)| after),
+            ..Reflection::test_default()
+        }
+    }
+
+    fn person(id: Option<&str>, name: Option<&str>) -> Person {
+        Person {
+            id: id.map(str::to_string),
+            name: name.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_group_by_id_with_name_fallback() {
+        let alex = person(Some("p1"), Some("Alex"));
+        let sam = person(None, Some("Sam"));
+        let reflections = vec![
+            reflection("2024-01-15T10:00:00Z", "joy", vec![alex.clone(), sam], 8.0, Some((5.0, 7.0))),
+            reflection("2024-02-01T10:00:00Z", "anger", vec![alex.clone(), alex.clone()], 4.0, Some((6.0, 3.0))),
+            reflection("2024-02-03T10:00:00Z", "joy", vec![alex, person(None, None)], 6.0, None),
+        ];
+        let result = compute_people_impact(&reflections, &PeopleImpactOptions::default());
+        assert_eq!(result.len(), 2);
+
+        let alex = &result[0];
+        assert_eq!(alex.person_id, "p1");
+        assert_eq!(alex.name.as_deref(), Some("Alex"));
+        assert_eq!(alex.count, 3);
+        assert_eq!(alex.average_intensity, Some(6.0));
+        assert_eq!(alex.mood_count, 2);
+        assert_eq!(alex.mean_mood_delta, Some(-0.5));
+        assert_eq!(alex.top_emotions[0].emotion_id, "joy");
+        assert_eq!(alex.top_emotions[0].count, 2);
+
+        let trend: Vec<(&str, usize)> = alex.trend.iter().map(|p| (p.date.as_str(), p.count)).collect();
+        assert_eq!(trend, vec![("2024-01", 1), ("2024-02", 2)]);
+
+        assert_eq!(result[1].person_id, "name:Sam");
+    }
+
+    #[test]
+    fn test_id_and_name_do_not_merge() {
+        let reflections = vec![reflection(
+            "2024-01-15T10:00:00Z",
+            "joy",
+            vec![person(Some("alice"), Some("Alice")), person(None, Some("alice"))],
+            5.0,
+            None,
+        )];
+        let result = compute_people_impact(&reflections, &PeopleImpactOptions::default());
+        let ids: Vec<&str> = result.iter().map(|p| p.person_id.as_str()).collect();
+        assert_eq!(ids, vec!["alice", "name:alice"]);
+        assert!(result.iter().all(|p| p.count == 1));
+    }
+
+    #[test]
+    fn test_top_n_and_trend_period() {
+        let alex = person(Some("p1"), None);
+        let reflections = vec![
+            reflection("2024-01-15T10:00:00Z", "joy", vec![alex.clone()], 5.0, None),
+            reflection("2024-01-16T10:00:00Z", "calm", vec![alex], 5.0, None),
+        ];
+        let options: PeopleImpactOptions =
+            serde_json::from_str(r#"{"topN": 1, "trendPeriod": "daily"}"#).unwrap();
+        let result = compute_people_impact(&reflections, &options);
+        assert_eq!(result[0].top_emotions.len(), 1);
+        assert_eq!(result[0].top_emotions[0].emotion_id, "calm");
+        assert_eq!(result[0].trend.len(), 2);
+        assert_eq!(result[0].trend[0].date, "2024-01-15");
+    }
+}
@@ -0,0 +1,431 @@
+use super::envelope::AnalyticsError;
+use super::statistics::{
+    apply_non_finite_policy, empty_statistics, percentile_key, requested_quantiles, Moments,
+    NonFinitePolicy, Percentiles, StatisticsOptions, StatisticsResult,
+};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+const DEFAULT_COMPRESSION: f64 = 100.0;
+const MIN_COMPRESSION: f64 = 10.0;
+const MAX_COMPRESSION: f64 = 10_000.0;
+
+/// Values buffered per compression pass, as a multiple of the compression
+const BUFFER_FACTOR: f64 = 10.0;
+
+/// Mergeable quantile sketch (merging t-digest) with exact count, sum, extremes and moments
+///
+/// Quantiles are approximate; accuracy is best in the tails and improves with
+/// `compression`, which bounds the number of centroids kept (about
+/// `compression` / 2 after each pass). Everything else in the derived
+/// `StatisticsResult` except `median`, `percentiles`, `iqr` and `mad` is exact
+/// and merges without loss.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "RawQuantileSketch")]
+pub struct QuantileSketch {
+    compression: f64,
+    count: u64,
+    dropped_count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: f64,
+    /// Sums of squared, cubed and fourth-power deviations from the mean
+    m2: f64,
+    m3: f64,
+    m4: f64,
+    /// `[mean, weight]` pairs in ascending mean order
+    centroids: Vec<(f64, f64)>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawQuantileSketch {
+    compression: f64,
+    count: u64,
+    #[serde(default)]
+    dropped_count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: f64,
+    m2: f64,
+    m3: f64,
+    m4: f64,
+    centroids: Vec<(f64, f64)>,
+}
+
+impl TryFrom<RawQuantileSketch> for QuantileSketch {
+    type Error = String;
+
+    fn try_from(raw: RawQuantileSketch) -> Result<Self, Self::Error> {
+        if !(MIN_COMPRESSION..=MAX_COMPRESSION).contains(&raw.compression) {
+            return Err(compression_error());
+        }
+        if raw.centroids.iter().any(|(_, weight)| *weight <= 0.0) {
+            return Err("centroid weights must be positive".to_string());
+        }
+        if raw.centroids.windows(2).any(|pair| pair[0].0 > pair[1].0) {
+            return Err("centroids must be sorted by mean".to_string());
+        }
+        if raw.min.is_some() != (raw.count > 0) || raw.max.is_some() != (raw.count > 0) {
+            return Err("min and max must be present exactly when count is positive".to_string());
+        }
+        let weight: f64 = raw.centroids.iter().map(|(_, weight)| weight).sum();
+        if (weight - raw.count as f64).abs() > 0.5 {
+            return Err("centroid weights do not add up to count".to_string());
+        }
+
+        Ok(QuantileSketch {
+            compression: raw.compression,
+            count: raw.count,
+            dropped_count: raw.dropped_count,
+            sum: raw.sum,
+            min: raw.min,
+            max: raw.max,
+            mean: raw.mean,
+            m2: raw.m2,
+            m3: raw.m3,
+            m4: raw.m4,
+            centroids: raw.centroids,
+        })
+    }
+}
+
+/// Options for building a sketch
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", try_from = "RawSketchOptions")]
+pub struct SketchOptions {
+    /// Accuracy/size trade-off, between 10 and 10000 (default 100)
+    pub compression: Option<f64>,
+    pub non_finite: NonFinitePolicy,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSketchOptions {
+    compression: Option<f64>,
+    #[serde(default)]
+    non_finite: NonFinitePolicy,
+}
+
+impl TryFrom<RawSketchOptions> for SketchOptions {
+    type Error = String;
+
+    fn try_from(raw: RawSketchOptions) -> Result<Self, Self::Error> {
+        if raw.compression.is_some_and(|c| !(MIN_COMPRESSION..=MAX_COMPRESSION).contains(&c)) {
+            return Err(compression_error());
+        }
+        if raw.non_finite == NonFinitePolicy::Propagate {
+            return Err("nonFinite \"propagate\" is not supported for sketches".to_string());
+        }
+        Ok(SketchOptions {
+            compression: raw.compression,
+            non_finite: raw.non_finite,
+        })
+    }
+}
+
+fn compression_error() -> String {
+    format!("compression must be between {} and {}", MIN_COMPRESSION, MAX_COMPRESSION)
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        QuantileSketch::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl QuantileSketch {
+    pub fn new(compression: f64) -> Self {
+        QuantileSketch {
+            compression,
+            count: 0,
+            dropped_count: 0,
+            sum: 0.0,
+            min: None,
+            max: None,
+            mean: 0.0,
+            m2: 0.0,
+            m3: 0.0,
+            m4: 0.0,
+            centroids: Vec::new(),
+        }
+    }
+
+    /// Build a sketch from raw values, applying the non-finite value policy
+    pub fn from_values(values: &[f64], options: &SketchOptions) -> Result<Self, AnalyticsError> {
+        let (values, dropped_count) = apply_non_finite_policy(values, options.non_finite)?;
+        let mut sketch = QuantileSketch::new(options.compression.unwrap_or(DEFAULT_COMPRESSION));
+        sketch.extend(&values);
+        sketch.dropped_count = dropped_count as u64;
+        Ok(sketch)
+    }
+
+    /// Add finite values
+    pub fn extend(&mut self, values: &[f64]) {
+        let chunk_size = (self.compression * BUFFER_FACTOR) as usize;
+        for chunk in values.chunks(chunk_size) {
+            for value in chunk {
+                self.combine_moments(1, *value, 0.0, 0.0, 0.0);
+                self.sum += value;
+                self.min = Some(self.min.map_or(*value, |min| min.min(*value)));
+                self.max = Some(self.max.map_or(*value, |max| max.max(*value)));
+            }
+            self.compress(chunk.iter().map(|value| (*value, 1.0)).collect());
+        }
+    }
+
+    /// Fold another sketch into this one, keeping this sketch's compression
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        if other.count == 0 {
+            self.dropped_count += other.dropped_count;
+            return;
+        }
+        self.combine_moments(other.count, other.mean, other.m2, other.m3, other.m4);
+        self.sum += other.sum;
+        self.dropped_count += other.dropped_count;
+        self.min = [self.min, other.min].into_iter().flatten().reduce(f64::min);
+        self.max = [self.max, other.max].into_iter().flatten().reduce(f64::max);
+        self.compress(other.centroids.clone());
+    }
+
+    /// Pairwise update of count, mean and central moment sums (Pébay 2008)
+    fn combine_moments(&mut self, count: u64, mean: f64, m2: f64, m3: f64, m4: f64) {
+        let (na, nb) = (self.count as f64, count as f64);
+        let n = na + nb;
+        let delta = mean - self.mean;
+        let delta_n = delta / n;
+
+        let new_m4 = self.m4
+            + m4
+            + delta * delta_n.powi(3) * na * nb * (na * na - na * nb + nb * nb)
+            + 6.0 * delta_n * delta_n * (na * na * m2 + nb * nb * self.m2)
+            + 4.0 * delta_n * (na * m3 - nb * self.m3);
+        let new_m3 = self.m3
+            + m3
+            + delta * delta_n * delta_n * na * nb * (na - nb)
+            + 3.0 * delta_n * (na * m2 - nb * self.m2);
+        let new_m2 = self.m2 + m2 + delta * delta_n * na * nb;
+
+        self.mean += nb * delta_n;
+        self.m2 = new_m2;
+        self.m3 = new_m3;
+        self.m4 = new_m4;
+        self.count += count;
+    }
+
+    /// Merge `incoming` centroids with the current ones under the k1 scale function
+    fn compress(&mut self, mut incoming: Vec<(f64, f64)>) {
+        incoming.append(&mut self.centroids);
+        if incoming.is_empty() {
+            return;
+        }
+        incoming.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let total: f64 = incoming.iter().map(|(_, weight)| weight).sum();
+        let mut merged = Vec::with_capacity(self.compression as usize);
+        let mut current = incoming[0];
+        let mut weight_before = 0.0;
+        let mut limit = self.quantile_limit(0.0);
+
+        for &(mean, weight) in &incoming[1..] {
+            if (weight_before + current.1 + weight) / total <= limit {
+                current.1 += weight;
+                current.0 += (mean - current.0) * weight / current.1;
+            } else {
+                weight_before += current.1;
+                merged.push(current);
+                limit = self.quantile_limit(weight_before / total);
+                current = (mean, weight);
+            }
+        }
+        merged.push(current);
+        self.centroids = merged;
+    }
+
+    /// Largest quantile a centroid starting at `q` may extend to: k1(q_end) - k1(q) <= 1
+    fn quantile_limit(&self, q: f64) -> f64 {
+        let k = self.compression / (2.0 * PI) * (2.0 * q - 1.0).asin() + 1.0;
+        let angle = (k * 2.0 * PI / self.compression).min(PI / 2.0);
+        (angle.sin() + 1.0) / 2.0
+    }
+
+    /// Approximate `q` quantile, interpolating between centroid centres
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let (min, max) = (self.min?, self.max?);
+        let centroids = &self.centroids;
+        let total = self.count as f64;
+        let target = q * total;
+
+        let first = centroids[0];
+        if target <= first.1 / 2.0 {
+            return Some(interpolate(min, first.0, target / (first.1 / 2.0)));
+        }
+        let last = centroids[centroids.len() - 1];
+        if target >= total - last.1 / 2.0 {
+            let span = last.1 / 2.0;
+            return Some(interpolate(last.0, max, 1.0 - (total - target) / span));
+        }
+
+        let mut centre = first.1 / 2.0;
+        for pair in centroids.windows(2) {
+            let next_centre = centre + (pair[0].1 + pair[1].1) / 2.0;
+            if target <= next_centre {
+                let fraction = (target - centre) / (next_centre - centre);
+                return Some(interpolate(pair[0].0, pair[1].0, fraction).clamp(min, max));
+            }
+            centre = next_centre;
+        }
+        Some(max)
+    }
+
+    /// Approximate median absolute deviation, treating each centroid as a point mass
+    fn mad(&self, median: f64) -> f64 {
+        let mut deviations: Vec<(f64, f64)> = self
+            .centroids
+            .iter()
+            .map(|(mean, weight)| ((mean - median).abs(), *weight))
+            .collect();
+        deviations.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let half = self.count as f64 / 2.0;
+        let mut cumulative = 0.0;
+        for (deviation, weight) in deviations {
+            cumulative += weight;
+            if cumulative >= half {
+                return deviation;
+            }
+        }
+        0.0
+    }
+
+    /// Summarise the sketch; `quantiles` and `variance` options apply, `interpolation` does not
+    pub fn statistics(&self, options: &StatisticsOptions) -> StatisticsResult {
+        let (min, max) = match (self.min, self.max) {
+            (Some(min), Some(max)) => (min, max),
+            _ => {
+                return StatisticsResult {
+                    dropped_count: self.dropped_count as usize,
+                    ..empty_statistics()
+                }
+            }
+        };
+
+        let quantile = |q: f64| self.quantile(q).unwrap_or(0.0);
+        let median = quantile(0.5);
+        let percentiles = Percentiles(
+            requested_quantiles(options)
+                .into_iter()
+                .map(|q| (percentile_key(q), quantile(q)))
+                .collect(),
+        );
+
+        let moments = Moments::from_central_sums(self.count as f64, self.m2, self.m3, self.m4);
+        let variance = moments.variance(options.variance);
+        let std_dev = variance.map(f64::sqrt);
+        let coefficient_of_variation = std_dev.filter(|_| self.mean != 0.0).map(|sd| sd / self.mean);
+
+        StatisticsResult {
+            count: self.count as usize,
+            dropped_count: self.dropped_count as usize,
+            sum: self.sum,
+            mean: self.mean,
+            median,
+            min,
+            max,
+            percentiles,
+            variance,
+            std_dev,
+            iqr: quantile(0.75) - quantile(0.25),
+            mad: self.mad(median),
+            coefficient_of_variation,
+            skewness: moments.skewness(options.variance),
+            kurtosis: moments.kurtosis(options.variance),
+            confidence_intervals: None,
+            weighted: None,
+        }
+    }
+}
+
+fn interpolate(from: f64, to: f64, fraction: f64) -> f64 {
+    from + (to - from) * fraction.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statistics::compute_statistics;
+
+    fn values(n: u32) -> Vec<f64> {
+        // Deterministic, unsorted spread over [0, n)
+        (0..n).map(|i| f64::from((i * 7919) % n)).collect()
+    }
+
+    #[test]
+    fn test_small_input_is_exact() {
+        let sketch = QuantileSketch::from_values(&[1.0, 2.0, 3.0, 4.0, 5.0], &SketchOptions::default()).unwrap();
+        assert_eq!(sketch.quantile(0.5), Some(3.0));
+        assert_eq!(sketch.quantile(0.0), Some(1.0));
+        assert_eq!(sketch.quantile(1.0), Some(5.0));
+    }
+
+    #[test]
+    fn test_large_input_quantiles_and_size() {
+        let data = values(100_000);
+        let sketch = QuantileSketch::from_values(&data, &SketchOptions::default()).unwrap();
+        assert!(sketch.centroids.len() <= 100);
+        for q in [0.01, 0.25, 0.5, 0.75, 0.99] {
+            let estimate = sketch.quantile(q).unwrap();
+            let exact = q * 99_999.0;
+            assert!((estimate - exact).abs() < 100_000.0 * 0.01, "q={} estimate={}", q, estimate);
+        }
+    }
+
+    #[test]
+    fn test_merge_matches_single_sketch_moments() {
+        let data = values(10_000);
+        let (left, right) = data.split_at(3_000);
+        let mut merged = QuantileSketch::from_values(left, &SketchOptions::default()).unwrap();
+        merged.merge(&QuantileSketch::from_values(right, &SketchOptions::default()).unwrap());
+
+        let options = StatisticsOptions::default();
+        let exact = compute_statistics(&data, &options).unwrap();
+        let approx = merged.statistics(&options);
+        assert_eq!(approx.count, 10_000);
+        assert_eq!(approx.min, exact.min);
+        assert_eq!(approx.max, exact.max);
+        assert!((approx.mean - exact.mean).abs() < 1e-9);
+        assert!((approx.variance.unwrap() - exact.variance.unwrap()).abs() / exact.variance.unwrap() < 1e-9);
+        assert!((approx.skewness.unwrap() - exact.skewness.unwrap()).abs() < 1e-9);
+        assert!((approx.kurtosis.unwrap() - exact.kurtosis.unwrap()).abs() < 1e-9);
+        assert!((approx.median - exact.median).abs() < 100.0);
+        assert!((approx.mad - exact.mad).abs() < 100.0);
+    }
+
+    #[test]
+    fn test_serialization_round_trip_and_validation() {
+        let sketch = QuantileSketch::from_values(&values(500), &SketchOptions::default()).unwrap();
+        let json = serde_json::to_string(&sketch).unwrap();
+        let restored: QuantileSketch = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, sketch);
+
+        let empty = serde_json::to_string(&QuantileSketch::default()).unwrap();
+        assert_eq!(serde_json::from_str::<QuantileSketch>(&empty).unwrap().statistics(&StatisticsOptions::default()).count, 0);
+
+        let tampered = json.replacen("\"count\":500", "\"count\":7", 1);
+        assert!(serde_json::from_str::<QuantileSketch>(&tampered).is_err());
+        assert!(serde_json::from_str::<SketchOptions>(r#"{"compression": 1}"#).is_err());
+    }
+
+    #[test]
+    fn test_non_finite_values() {
+        assert!(QuantileSketch::from_values(&[1.0, f64::NAN], &SketchOptions::default()).is_err());
+        let options: SketchOptions = serde_json::from_str(r#"{"nonFinite": "drop"}"#).unwrap();
+        let sketch = QuantileSketch::from_values(&[1.0, f64::NAN, 3.0], &options).unwrap();
+        let result = sketch.statistics(&StatisticsOptions::default());
+        assert_eq!(result.count, 2);
+        assert_eq!(result.dropped_count, 1);
+        assert_eq!(result.mean, 2.0);
+    }
+}
@@ -1,59 +1,585 @@
-use std::collections::HashMap;
+use super::bootstrap::{bootstrap_intervals, BootstrapOptions, ConfidenceIntervals};
+use super::envelope::{AnalyticsError, ErrorKind};
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+const DEFAULT_QUANTILES: [f64; 7] = [0.10, 0.25, 0.50, 0.75, 0.90, 0.95, 0.99];
 
 /// Statistical result
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StatisticsResult {
+    pub count: usize,
+    /// Non-finite values removed by the `drop` policy
+    pub dropped_count: usize,
+    pub sum: f64,
     pub mean: f64,
     pub median: f64,
     pub min: f64,
     pub max: f64,
-    pub percentiles: HashMap<String, f64>,
+    pub percentiles: Percentiles,
+    /// Variance, `null` when fewer than two values are given for sample variance
+    pub variance: Option<f64>,
+    pub std_dev: Option<f64>,
+    /// Interquartile range (p75 - p25) using the selected interpolation
+    pub iqr: f64,
+    /// Median absolute deviation from the median (unscaled)
+    pub mad: f64,
+    /// Standard deviation divided by mean, `null` when the mean is zero
+    pub coefficient_of_variation: Option<f64>,
+    /// Skewness, `null` for constant data or too few values
+    pub skewness: Option<f64>,
+    /// Excess kurtosis (normal distribution = 0), `null` for constant data or too few values
+    pub kurtosis: Option<f64>,
+    /// Bootstrap confidence intervals, present when `bootstrap` options are given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence_intervals: Option<ConfidenceIntervals>,
+    /// Weighted metrics, present when the input carries weights
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weighted: Option<WeightedStatistics>,
+}
+
+/// Weighted counterparts of the central metrics
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeightedStatistics {
+    pub total_weight: f64,
+    /// Weighted mean, `null` when the total weight is zero
+    pub mean: Option<f64>,
+    /// Weighted variance, `null` when the sample correction leaves no degrees of freedom
+    pub variance: Option<f64>,
+    pub std_dev: Option<f64>,
+    /// Weighted median and percentiles, `null` when the total weight is zero
+    pub median: Option<f64>,
+    pub percentiles: Percentiles<Option<f64>>,
+}
+
+/// One input entry: a bare number (weight 1) or a `{value, weight}` object
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(untagged)]
+pub enum StatisticsInput {
+    Value(Option<f64>),
+    Weighted { value: Option<f64>, weight: f64 },
+}
+
+/// Options for statistical aggregation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatisticsOptions {
+    /// Quantiles to report, each between 0 and 1 (default p10/25/50/75/90/95/99)
+    #[serde(default, deserialize_with = "deserialize_quantiles")]
+    pub quantiles: Option<Vec<f64>>,
+    /// How to estimate quantiles that fall between two data points
+    #[serde(default)]
+    pub interpolation: Interpolation,
+    /// Whether variance and the shape metrics use sample or population formulas
+    #[serde(default)]
+    pub variance: VarianceMode,
+    /// What to do with NaN and infinite values (`null` in the input JSON counts as NaN)
+    #[serde(default)]
+    pub non_finite: NonFinitePolicy,
+    /// Bootstrap confidence intervals for mean, median and the requested percentiles
+    pub bootstrap: Option<BootstrapOptions>,
+    /// Meaning of weights for sample variance of weighted input
+    #[serde(default)]
+    pub weight_kind: WeightKind,
+}
+
+/// Meaning of weights, which decides the sample variance correction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WeightKind {
+    /// Weights count repeated observations (e.g. cohort sizes); divisor is total weight - 1
+    #[default]
+    Frequency,
+    /// Weights express relative importance (e.g. recency); divisor is V1 - V2 / V1
+    Reliability,
+}
+
+/// Handling of NaN and infinite input values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NonFinitePolicy {
+    /// Fail with an error pointing at the first non-finite value
+    #[default]
+    Reject,
+    /// Leave non-finite values out and report how many were removed
+    Drop,
+    /// Compute with them; any NaN makes every metric NaN (`null` in JSON)
+    Propagate,
+}
+
+/// Divisor and bias correction for variance, skewness and kurtosis
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum VarianceMode {
+    /// Bessel-corrected variance (n - 1) with adjusted skewness G1 and kurtosis G2, as pandas reports
+    #[default]
+    Sample,
+    /// Population variance (n) with moment skewness g1 and kurtosis g2
+    Population,
+}
+
+/// Quantile estimation method, named after the numpy/pandas options
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Interpolation {
+    /// Value at the nearest rank, rounding halves away from zero
+    #[default]
+    Nearest,
+    /// Linear interpolation between neighbouring ranks (Hyndman–Fan type 7, the R and pandas default)
+    #[serde(alias = "type7", alias = "hyndmanFan7")]
+    Linear,
+    /// Lower of the two neighbouring values
+    Lower,
+    /// Higher of the two neighbouring values
+    Higher,
+    /// Mean of the two neighbouring values
+    Midpoint,
+}
+
+/// Per-percentile values keyed as `p50`, `p2.5`, ... in ascending quantile order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Percentiles<T = f64>(pub Vec<(String, T)>);
+
+impl<T: Serialize> Serialize for Percentiles<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(key, value)| (key, value)))
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Percentiles<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PercentilesVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>> Visitor<'de> for PercentilesVisitor<T> {
+            type Value = Percentiles<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of percentile keys to values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Percentiles<T>, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry::<String, T>()? {
+                    entries.push(entry);
+                }
+                Ok(Percentiles(entries))
+            }
+        }
+
+        deserializer.deserialize_map(PercentilesVisitor(PhantomData))
+    }
+}
+
+fn deserialize_quantiles<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<f64>>, D::Error> {
+    let quantiles = Option::<Vec<f64>>::deserialize(deserializer)?;
+    if let Some(quantiles) = &quantiles {
+        if let Some(q) = quantiles.iter().find(|q| !(0.0..=1.0).contains(*q)) {
+            return Err(serde::de::Error::custom(format!(
+                "quantile {} is outside the range 0 to 1",
+                q
+            )));
+        }
+    }
+    Ok(quantiles)
 }
 
-/// Compute statistical aggregations
-pub fn compute_statistics(values: &[f64]) -> StatisticsResult {
+/// Percentile key for a quantile, e.g. 0.5 -> "p50", 0.025 -> "p2.5"
+pub fn percentile_key(q: f64) -> String {
+    let percent = (q * 100.0 * 1e6).round() / 1e6;
+    format!("p{}", percent)
+}
+
+/// Arithmetic mean, `None` for no values
+pub fn mean(values: &[f64]) -> Option<f64> {
     if values.is_empty() {
-        return StatisticsResult {
-            mean: 0.0,
-            median: 0.0,
-            min: 0.0,
-            max: 0.0,
-            percentiles: HashMap::new(),
-        };
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// Estimate the `q` quantile of ascending `sorted` values
+pub fn quantile(sorted: &[f64], q: f64, interpolation: Interpolation) -> f64 {
+    quantile_at(sorted.len(), |index| sorted[index], q, interpolation)
+}
+
+/// Estimate the `q` quantile of `len` ascending values read through `value_at`
+pub fn quantile_at(
+    len: usize,
+    value_at: impl Fn(usize) -> f64,
+    q: f64,
+    interpolation: Interpolation,
+) -> f64 {
+    let position = q * (len - 1) as f64;
+    let lower = value_at(position.floor() as usize);
+    let upper = value_at((position.ceil() as usize).min(len - 1));
+
+    match interpolation {
+        Interpolation::Nearest => value_at((position.round() as usize).min(len - 1)),
+        Interpolation::Linear => lower + (position - position.floor()) * (upper - lower),
+        Interpolation::Lower => lower,
+        Interpolation::Higher => upper,
+        Interpolation::Midpoint => (lower + upper) / 2.0,
+    }
+}
+
+/// Compute statistical aggregations, applying the non-finite value policy first
+pub fn compute_statistics(
+    values: &[f64],
+    options: &StatisticsOptions,
+) -> Result<StatisticsResult, AnalyticsError> {
+    let (values, dropped_count) = apply_non_finite_policy(values, options.non_finite)?;
+
+    let mut result = if values.iter().any(|v| v.is_nan()) {
+        nan_statistics(values.len(), options)
+    } else {
+        summarize(&values, options)
+    };
+    result.dropped_count = dropped_count;
+    Ok(result)
+}
+
+/// Apply a non-finite value policy, returning the values to use and how many were dropped
+pub fn apply_non_finite_policy(
+    values: &[f64],
+    policy: NonFinitePolicy,
+) -> Result<(Cow<'_, [f64]>, usize), AnalyticsError> {
+    match policy {
+        NonFinitePolicy::Reject => {
+            if let Some(index) = values.iter().position(|v| !v.is_finite()) {
+                return Err(AnalyticsError::new(
+                    ErrorKind::InvalidInput,
+                    &format!("$[{}]", index),
+                    format!("non-finite value {} (use nonFinite \"drop\" or \"propagate\")", values[index]),
+                ));
+            }
+            Ok((Cow::Borrowed(values), 0))
+        }
+        NonFinitePolicy::Drop => {
+            let finite: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+            let dropped = values.len() - finite.len();
+            Ok((Cow::Owned(finite), dropped))
+        }
+        NonFinitePolicy::Propagate => Ok((Cow::Borrowed(values), 0)),
+    }
+}
+
+/// Result for input containing NaN under the propagate policy
+fn nan_statistics(count: usize, options: &StatisticsOptions) -> StatisticsResult {
+    StatisticsResult {
+        count,
+        dropped_count: 0,
+        sum: f64::NAN,
+        mean: f64::NAN,
+        median: f64::NAN,
+        min: f64::NAN,
+        max: f64::NAN,
+        percentiles: Percentiles(
+            requested_quantiles(options)
+                .into_iter()
+                .map(|q| (percentile_key(q), f64::NAN))
+                .collect(),
+        ),
+        variance: Some(f64::NAN),
+        std_dev: Some(f64::NAN),
+        iqr: f64::NAN,
+        mad: f64::NAN,
+        coefficient_of_variation: Some(f64::NAN),
+        skewness: Some(f64::NAN),
+        kurtosis: Some(f64::NAN),
+        confidence_intervals: None,
+        weighted: None,
+    }
+}
+
+/// Quantiles from the options (or the defaults), ascending and deduplicated
+pub fn requested_quantiles(options: &StatisticsOptions) -> Vec<f64> {
+    let mut quantiles = options
+        .quantiles
+        .clone()
+        .unwrap_or_else(|| DEFAULT_QUANTILES.to_vec());
+    quantiles.sort_by(|a, b| a.total_cmp(b));
+    quantiles.dedup();
+    quantiles
+}
+
+/// Result for empty input
+pub fn empty_statistics() -> StatisticsResult {
+    StatisticsResult {
+        count: 0,
+        dropped_count: 0,
+        sum: 0.0,
+        mean: 0.0,
+        median: 0.0,
+        min: 0.0,
+        max: 0.0,
+        percentiles: Percentiles::default(),
+        variance: None,
+        std_dev: None,
+        iqr: 0.0,
+        mad: 0.0,
+        coefficient_of_variation: None,
+        skewness: None,
+        kurtosis: None,
+        confidence_intervals: None,
+        weighted: None,
+    }
+}
+
+/// Descriptive statistics of NaN-free values
+fn summarize(values: &[f64], options: &StatisticsOptions) -> StatisticsResult {
+    if values.is_empty() {
+        return empty_statistics();
     }
 
     let mut sorted = values.to_vec();
-    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    sorted.sort_by(|a, b| a.total_cmp(b));
 
     let sum: f64 = values.iter().sum();
     let mean = sum / values.len() as f64;
     let min = sorted[0];
     let max = sorted[sorted.len() - 1];
 
-    // Calculate median
-    let median = if sorted.len() % 2 == 0 {
-        (sorted[sorted.len() / 2 - 1] + sorted[sorted.len() / 2]) / 2.0
-    } else {
-        sorted[sorted.len() / 2]
-    };
+    let median = median_of_sorted(&sorted);
 
-    // Calculate percentiles
-    let mut percentiles = HashMap::new();
-    let percentiles_to_calc = [10, 25, 50, 75, 90, 95, 99];
-    
-    for p in &percentiles_to_calc {
-        let index = ((*p as f64 / 100.0) * (sorted.len() - 1) as f64).round() as usize;
-        let value = sorted[index.min(sorted.len() - 1)];
-        percentiles.insert(format!("p{}", p), value);
-    }
+    // Calculate percentiles in ascending quantile order
+    let percentiles = Percentiles(
+        requested_quantiles(options)
+            .into_iter()
+            .map(|q| (percentile_key(q), quantile(&sorted, q, options.interpolation)))
+            .collect(),
+    );
+
+    let iqr = quantile(&sorted, 0.75, options.interpolation)
+        - quantile(&sorted, 0.25, options.interpolation);
+
+    let mut deviations: Vec<f64> = sorted.iter().map(|v| (v - median).abs()).collect();
+    deviations.sort_by(|a, b| a.total_cmp(b));
+    let mad = median_of_sorted(&deviations);
+
+    let moments = Moments::new(values, mean);
+    let variance = moments.variance(options.variance);
+    let std_dev = variance.map(f64::sqrt);
+    let coefficient_of_variation = std_dev.filter(|_| mean != 0.0).map(|sd| sd / mean);
 
     StatisticsResult {
+        count: values.len(),
+        dropped_count: 0,
+        sum,
         mean,
         median,
         min,
         max,
         percentiles,
+        variance,
+        std_dev,
+        iqr,
+        mad,
+        coefficient_of_variation,
+        skewness: moments.skewness(options.variance),
+        kurtosis: moments.kurtosis(options.variance),
+        confidence_intervals: options.bootstrap.as_ref().map(|bootstrap| {
+            bootstrap_intervals(&sorted, &requested_quantiles(options), options.interpolation, bootstrap)
+        }),
+        weighted: None,
+    }
+}
+
+/// Compute statistics of weighted input: the unweighted metrics of the values plus weighted metrics
+///
+/// Weights must be finite and non-negative. The non-finite value policy applies
+/// to values; dropped values take their weights with them.
+pub fn compute_weighted_statistics(
+    entries: &[(f64, f64)],
+    options: &StatisticsOptions,
+) -> Result<StatisticsResult, AnalyticsError> {
+    let invalid_weight = entries
+        .iter()
+        .position(|(_, weight)| !(weight.is_finite() && *weight >= 0.0));
+    if let Some(index) = invalid_weight {
+        return Err(AnalyticsError::new(
+            ErrorKind::InvalidInput,
+            &format!("$[{}].weight", index),
+            format!("weight {} must be a finite, non-negative number", entries[index].1),
+        ));
+    }
+    if options.non_finite == NonFinitePolicy::Reject {
+        if let Some(index) = entries.iter().position(|(value, _)| !value.is_finite()) {
+            return Err(AnalyticsError::new(
+                ErrorKind::InvalidInput,
+                &format!("$[{}].value", index),
+                format!("non-finite value {} (use nonFinite \"drop\" or \"propagate\")", entries[index].0),
+            ));
+        }
+    }
+
+    let values: Vec<f64> = entries.iter().map(|(value, _)| *value).collect();
+    let mut result = compute_statistics(&values, options)?;
+
+    let kept: Vec<(f64, f64)> = entries
+        .iter()
+        .copied()
+        .filter(|(value, weight)| {
+            *weight > 0.0 && (options.non_finite != NonFinitePolicy::Drop || value.is_finite())
+        })
+        .collect();
+    result.weighted = Some(weighted_statistics(kept, options));
+    Ok(result)
+}
+
+fn weighted_statistics(
+    mut entries: Vec<(f64, f64)>,
+    options: &StatisticsOptions,
+) -> WeightedStatistics {
+    let quantiles = requested_quantiles(options);
+    let total_weight: f64 = entries.iter().map(|(_, weight)| weight).sum();
+
+    if entries.iter().any(|(value, _)| value.is_nan()) || total_weight == 0.0 {
+        let fill = (total_weight > 0.0).then_some(f64::NAN);
+        return WeightedStatistics {
+            total_weight,
+            mean: fill,
+            variance: fill,
+            std_dev: fill,
+            median: fill,
+            percentiles: Percentiles(quantiles.into_iter().map(|q| (percentile_key(q), fill)).collect()),
+        };
+    }
+
+    let mean = entries.iter().map(|(value, weight)| value * weight).sum::<f64>() / total_weight;
+    let squares: f64 = entries.iter().map(|(value, weight)| weight * (value - mean).powi(2)).sum();
+    let divisor = match (options.variance, options.weight_kind) {
+        (VarianceMode::Population, _) => total_weight,
+        (VarianceMode::Sample, WeightKind::Frequency) => total_weight - 1.0,
+        (VarianceMode::Sample, WeightKind::Reliability) => {
+            let squared_weights: f64 = entries.iter().map(|(_, weight)| weight * weight).sum();
+            total_weight - squared_weights / total_weight
+        }
+    };
+    let variance = (divisor > 0.0).then(|| squares / divisor);
+
+    entries.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let percentiles = Percentiles(
+        quantiles
+            .into_iter()
+            .map(|q| (percentile_key(q), Some(weighted_quantile(&entries, total_weight, q))))
+            .collect(),
+    );
+
+    WeightedStatistics {
+        total_weight,
+        mean: Some(mean),
+        variance,
+        std_dev: variance.map(f64::sqrt),
+        median: Some(weighted_quantile(&entries, total_weight, 0.5)),
+        percentiles,
+    }
+}
+
+/// Weighted quantile of value-sorted entries, interpolating linearly between
+/// the weight midpoints of neighbouring values (Hyndman–Fan type 5 for equal weights)
+fn weighted_quantile(sorted: &[(f64, f64)], total_weight: f64, q: f64) -> f64 {
+    let target = q * total_weight;
+    let mut previous: Option<(f64, f64)> = None; // (midpoint, value)
+    let mut cumulative = 0.0;
+
+    for (value, weight) in sorted {
+        let midpoint = cumulative + weight / 2.0;
+        if target <= midpoint {
+            return match previous {
+                Some((previous_midpoint, previous_value)) => {
+                    let fraction = (target - previous_midpoint) / (midpoint - previous_midpoint);
+                    previous_value + fraction * (value - previous_value)
+                }
+                None => *value,
+            };
+        }
+        previous = Some((midpoint, *value));
+        cumulative += weight;
+    }
+    sorted[sorted.len() - 1].0
+}
+
+fn median_of_sorted(sorted: &[f64]) -> f64 {
+    if sorted.len().is_multiple_of(2) {
+        (sorted[sorted.len() / 2 - 1] + sorted[sorted.len() / 2]) / 2.0
+    } else {
+        sorted[sorted.len() / 2]
+    }
+}
+
+/// Central moments m2, m3, m4 (divided by n) of a sample
+pub struct Moments {
+    n: f64,
+    m2: f64,
+    m3: f64,
+    m4: f64,
+}
+
+impl Moments {
+    /// Moments from sums of squared, cubed and fourth-power deviations from the mean
+    pub fn from_central_sums(n: f64, s2: f64, s3: f64, s4: f64) -> Self {
+        Moments {
+            n,
+            m2: s2 / n,
+            m3: s3 / n,
+            m4: s4 / n,
+        }
+    }
+
+    fn new(values: &[f64], mean: f64) -> Self {
+        let n = values.len() as f64;
+        let (mut m2, mut m3, mut m4) = (0.0, 0.0, 0.0);
+        for value in values {
+            let d = value - mean;
+            let d2 = d * d;
+            m2 += d2;
+            m3 += d2 * d;
+            m4 += d2 * d2;
+        }
+        Moments::from_central_sums(n, m2, m3, m4)
+    }
+
+    pub fn variance(&self, mode: VarianceMode) -> Option<f64> {
+        match mode {
+            VarianceMode::Population => Some(self.m2),
+            VarianceMode::Sample if self.n >= 2.0 => Some(self.m2 * self.n / (self.n - 1.0)),
+            VarianceMode::Sample => None,
+        }
+    }
+
+    pub fn skewness(&self, mode: VarianceMode) -> Option<f64> {
+        if self.m2 == 0.0 {
+            return None;
+        }
+        let g1 = self.m3 / self.m2.powf(1.5);
+        match mode {
+            VarianceMode::Population => Some(g1),
+            VarianceMode::Sample if self.n >= 3.0 => {
+                Some(g1 * (self.n * (self.n - 1.0)).sqrt() / (self.n - 2.0))
+            }
+            VarianceMode::Sample => None,
+        }
+    }
+
+    pub fn kurtosis(&self, mode: VarianceMode) -> Option<f64> {
+        if self.m2 == 0.0 {
+            return None;
+        }
+        let g2 = self.m4 / (self.m2 * self.m2) - 3.0;
+        match mode {
+            VarianceMode::Population => Some(g2),
+            VarianceMode::Sample if self.n >= 4.0 => {
+                let n = self.n;
+                Some((n - 1.0) / ((n - 2.0) * (n - 3.0)) * ((n + 1.0) * g2 + 6.0))
+            }
+            VarianceMode::Sample => None,
+        }
     }
 }
 
@@ -61,22 +587,203 @@ pub fn compute_statistics(values: &[f64]) -> StatisticsResult {
 mod tests {
     use super::*;
 
+    fn percentile(result: &StatisticsResult, key: &str) -> Option<f64> {
+        result.percentiles.0.iter().find(|(k, _)| k == key).map(|(_, value)| *value)
+    }
+
     #[test]
     fn test_compute_statistics() {
         let values = vec![1.0, 2.0, 3.0, 4.0, 5.0];
-        let result = compute_statistics(&values);
+        let result = compute_statistics(&values, &StatisticsOptions::default()).unwrap();
 
         assert_eq!(result.mean, 3.0);
         assert_eq!(result.median, 3.0);
         assert_eq!(result.min, 1.0);
         assert_eq!(result.max, 5.0);
-        assert!(result.percentiles.contains_key("p50"));
+        assert!(percentile(&result, "p50").is_some());
     }
 
     #[test]
     fn test_compute_statistics_empty() {
-        let result = compute_statistics(&[]);
+        let result = compute_statistics(&[], &StatisticsOptions::default()).unwrap();
         assert_eq!(result.mean, 0.0);
         assert_eq!(result.median, 0.0);
     }
+
+    #[test]
+    fn test_default_percentiles_are_ordered() {
+        let values: Vec<f64> = (1..=100).map(f64::from).collect();
+        let result = compute_statistics(&values, &StatisticsOptions::default()).unwrap();
+        let keys: Vec<&str> = result.percentiles.0.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["p10", "p25", "p50", "p75", "p90", "p95", "p99"]);
+        let json = serde_json::to_string(&result.percentiles).unwrap();
+        assert!(json.starts_with("{\"p10\":"));
+    }
+
+    #[test]
+    fn test_interpolation_methods() {
+        let sorted = [1.0, 2.0, 3.0, 4.0];
+        // position = 0.5 * 3 = 1.5
+        assert_eq!(quantile(&sorted, 0.5, Interpolation::Linear), 2.5);
+        assert_eq!(quantile(&sorted, 0.5, Interpolation::Lower), 2.0);
+        assert_eq!(quantile(&sorted, 0.5, Interpolation::Higher), 3.0);
+        assert_eq!(quantile(&sorted, 0.5, Interpolation::Midpoint), 2.5);
+        assert_eq!(quantile(&sorted, 0.5, Interpolation::Nearest), 3.0);
+        // position = 0.1 * 3 = 0.3, matching numpy.quantile(..., method="linear")
+        assert!((quantile(&sorted, 0.1, Interpolation::Linear) - 1.3).abs() < 1e-12);
+        assert_eq!(quantile(&[5.0], 0.9, Interpolation::Linear), 5.0);
+    }
+
+    #[test]
+    fn test_custom_quantiles() {
+        let options: StatisticsOptions =
+            serde_json::from_str(r#"{"quantiles": [0.975, 0.025, 0.5], "interpolation": "type7"}"#).unwrap();
+        assert_eq!(options.interpolation, Interpolation::Linear);
+        let result = compute_statistics(&[1.0, 2.0, 3.0, 4.0, 5.0], &options).unwrap();
+        let keys: Vec<&str> = result.percentiles.0.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, vec!["p2.5", "p50", "p97.5"]);
+        assert_eq!(percentile(&result, "p50"), Some(3.0));
+        assert!(serde_json::from_str::<StatisticsOptions>(r#"{"quantiles": [1.5]}"#).is_err());
+    }
+
+    #[test]
+    fn test_dispersion_metrics() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let result = compute_statistics(&values, &StatisticsOptions::default()).unwrap();
+        assert_eq!(result.count, 8);
+        assert_eq!(result.sum, 40.0);
+        assert!((result.variance.unwrap() - 32.0 / 7.0).abs() < 1e-12);
+        assert_eq!(result.mad, 0.5);
+        assert_eq!(result.iqr, 1.0);
+
+        let options: StatisticsOptions = serde_json::from_str(r#"{"variance": "population"}"#).unwrap();
+        let result = compute_statistics(&values, &options).unwrap();
+        assert_eq!(result.variance, Some(4.0));
+        assert_eq!(result.std_dev, Some(2.0));
+        assert_eq!(result.coefficient_of_variation, Some(0.4));
+    }
+
+    #[test]
+    fn test_shape_metrics() {
+        // Reference values from pandas Series.skew() / Series.kurt()
+        let values = [1.0, 2.0, 3.0, 4.0, 10.0];
+        let result = compute_statistics(&values, &StatisticsOptions::default()).unwrap();
+        assert!((result.skewness.unwrap() - 1.697056).abs() < 1e-6);
+        assert!((result.kurtosis.unwrap() - 3.152).abs() < 1e-6);
+
+        let constant = compute_statistics(&[3.0, 3.0, 3.0, 3.0], &StatisticsOptions::default()).unwrap();
+        assert_eq!(constant.variance, Some(0.0));
+        assert_eq!(constant.skewness, None);
+        assert_eq!(constant.kurtosis, None);
+
+        let single = compute_statistics(&[3.0], &StatisticsOptions::default()).unwrap();
+        assert_eq!(single.variance, None);
+        assert_eq!(single.std_dev, None);
+    }
+
+    #[test]
+    fn test_non_finite_policies() {
+        let values = [3.0, f64::NAN, 1.0, f64::INFINITY, 2.0];
+
+        let err = compute_statistics(&values, &StatisticsOptions::default()).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::InvalidInput);
+        assert_eq!(err.path, "$[1]");
+
+        let options: StatisticsOptions = serde_json::from_str(r#"{"nonFinite": "drop"}"#).unwrap();
+        let result = compute_statistics(&values, &options).unwrap();
+        assert_eq!(result.count, 3);
+        assert_eq!(result.dropped_count, 2);
+        assert_eq!(result.median, 2.0);
+
+        let options: StatisticsOptions = serde_json::from_str(r#"{"nonFinite": "propagate"}"#).unwrap();
+        let result = compute_statistics(&values, &options).unwrap();
+        assert_eq!(result.count, 5);
+        assert!(result.median.is_nan());
+        assert!(percentile(&result, "p50").unwrap().is_nan());
+        let json = serde_json::to_value(&result).unwrap();
+        assert!(json["mean"].is_null());
+
+        let result = compute_statistics(&[1.0, f64::INFINITY, 2.0], &options).unwrap();
+        assert_eq!(result.max, f64::INFINITY);
+        assert_eq!(result.median, 2.0);
+    }
+
+    #[test]
+    fn test_weighted_statistics() {
+        let entries = [(1.0, 1.0), (2.0, 3.0), (4.0, 0.0), (10.0, 1.0)];
+        let result = compute_weighted_statistics(&entries, &StatisticsOptions::default()).unwrap();
+        assert_eq!(result.count, 4);
+        assert_eq!(result.mean, 4.25);
+
+        let weighted = result.weighted.unwrap();
+        assert_eq!(weighted.total_weight, 5.0);
+        assert_eq!(weighted.mean, Some(3.4));
+        // Frequency weights: same as the expanded sample [1, 2, 2, 2, 10]
+        let expanded = compute_statistics(&[1.0, 2.0, 2.0, 2.0, 10.0], &StatisticsOptions::default()).unwrap();
+        assert!((weighted.variance.unwrap() - expanded.variance.unwrap()).abs() < 1e-12);
+        assert_eq!(weighted.median, Some(2.0));
+
+        let options: StatisticsOptions =
+            serde_json::from_str(r#"{"variance": "population", "quantiles": [0.5]}"#).unwrap();
+        let weighted = compute_weighted_statistics(&entries, &options).unwrap().weighted.unwrap();
+        assert!((weighted.variance.unwrap() - 11.04).abs() < 1e-12);
+        assert_eq!(weighted.percentiles.0, vec![("p50".to_string(), Some(2.0))]);
+
+        let weighted = compute_weighted_statistics(&[(1.0, 0.0), (3.0, 0.0)], &options)
+            .unwrap()
+            .weighted
+            .unwrap();
+        assert_eq!(weighted.total_weight, 0.0);
+        assert_eq!((weighted.mean, weighted.median, weighted.variance), (None, None, None));
+        assert_eq!(weighted.percentiles.0, vec![("p50".to_string(), None)]);
+    }
+
+    #[test]
+    fn test_weighted_quantile_interpolation_and_validation() {
+        let sorted = [(1.0, 1.0), (2.0, 1.0), (3.0, 1.0), (4.0, 1.0)];
+        assert_eq!(weighted_quantile(&sorted, 4.0, 0.5), 2.5);
+        assert_eq!(weighted_quantile(&sorted, 4.0, 0.0), 1.0);
+        assert_eq!(weighted_quantile(&sorted, 4.0, 1.0), 4.0);
+
+        let options: StatisticsOptions = serde_json::from_str(r#"{"weightKind": "reliability"}"#).unwrap();
+        let weighted = compute_weighted_statistics(&[(1.0, 0.5), (3.0, 0.5)], &options)
+            .unwrap()
+            .weighted
+            .unwrap();
+        assert_eq!(weighted.variance, Some(2.0));
+
+        let err = compute_weighted_statistics(&[(1.0, -1.0)], &StatisticsOptions::default()).unwrap_err();
+        assert_eq!(err.path, "$[0].weight");
+        let err = compute_weighted_statistics(&[(1.0, 1.0), (f64::NAN, 2.0)], &StatisticsOptions::default())
+            .unwrap_err();
+        assert_eq!(err.path, "$[1].value");
+    }
+
+    #[test]
+    fn test_weighted_nan_propagates() {
+        let options: StatisticsOptions =
+            serde_json::from_str(r#"{"nonFinite": "propagate", "quantiles": [0.25, 0.75]}"#).unwrap();
+        let weighted = compute_weighted_statistics(&[(1.0, 1.0), (f64::NAN, 2.0)], &options)
+            .unwrap()
+            .weighted
+            .unwrap();
+        assert_eq!(weighted.total_weight, 3.0);
+        let fields = [weighted.mean, weighted.variance, weighted.std_dev, weighted.median];
+        assert!(fields.iter().all(|field| field.is_some_and(f64::is_nan)));
+        assert!(weighted.percentiles.0.iter().all(|(_, value)| value.is_some_and(f64::is_nan)));
+    }
+
+    #[test]
+    fn test_bootstrap_option() {
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let result = compute_statistics(&values, &StatisticsOptions::default()).unwrap();
+        assert!(result.confidence_intervals.is_none());
+
+        let options: StatisticsOptions =
+            serde_json::from_str(r#"{"quantiles": [0.5], "bootstrap": {"seed": 1, "method": "bca"}}"#).unwrap();
+        let intervals = compute_statistics(&values, &options).unwrap().confidence_intervals.unwrap();
+        assert_eq!(intervals.resamples, 1000);
+        assert!(intervals.mean.lower <= 3.5 && 3.5 <= intervals.mean.upper);
+        assert_eq!(intervals.percentiles.0.len(), 1);
+    }
 }
@@ -1,39 +1,174 @@
 use super::{Reflection, TimePattern, EmotionCount, TimePatternsResult};
+use super::datetime::{parse_timestamp, DateTime};
+use super::filter::{apply_filter, ReflectionFilter};
+use super::statistics::mean;
+use super::timezone::TimeZone;
+use serde::Deserialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::hash::Hash;
 
 const DAY_NAMES: [&str; 7] = [
     "sunday", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday",
 ];
 
-const TIME_OF_DAY_NAMES: [&str; 4] = ["morning", "afternoon", "evening", "night"];
+const HOUR_NAMES: [&str; 24] = [
+    "00", "01", "02", "03", "04", "05", "06", "07", "08", "09", "10", "11",
+    "12", "13", "14", "15", "16", "17", "18", "19", "20", "21", "22", "23",
+];
+
+pub const DEFAULT_TOP_EMOTIONS: usize = 5;
+
+/// Localized day names (Sunday first), keyed by language subtag
+const DAY_NAME_LOCALES: &[(&str, [&str; 7])] = &[
+    ("de", ["Sonntag", "Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag"]),
+    ("en", ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"]),
+    ("es", ["domingo", "lunes", "martes", "miércoles", "jueves", "viernes", "sábado"]),
+    ("fr", ["dimanche", "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi"]),
+    ("it", ["domenica", "lunedì", "martedì", "mercoledì", "giovedì", "venerdì", "sabato"]),
+    ("ja", ["日曜日", "月曜日", "火曜日", "水曜日", "木曜日", "金曜日", "土曜日"]),
+    ("ko", ["일요일", "월요일", "화요일", "수요일", "목요일", "금요일", "토요일"]),
+    ("nl", ["zondag", "maandag", "dinsdag", "woensdag", "donderdag", "vrijdag", "zaterdag"]),
+    ("pt", ["domingo", "segunda-feira", "terça-feira", "quarta-feira", "quinta-feira", "sexta-feira", "sábado"]),
+    ("sv", ["söndag", "måndag", "tisdag", "onsdag", "torsdag", "fredag", "lördag"]),
+    ("zh", ["星期日", "星期一", "星期二", "星期三", "星期四", "星期五", "星期六"]),
+];
+
+/// Options for time pattern computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimePatternsOptions {
+    /// IANA zone, POSIX rule or fixed offset to bucket by; unset keeps each timestamp's own wall clock
+    pub timezone: Option<TimeZone>,
+    /// Named time-of-day ranges, checked in order; defaults to morning/afternoon/evening/night
+    pub time_of_day: Option<Vec<TimeOfDayRange>>,
+    /// Number of top emotions kept per period (default 5)
+    pub top_n: Option<usize>,
+    /// Also emit 24 hour-of-day buckets ("00" to "23")
+    #[serde(default)]
+    pub include_hours: bool,
+    /// BCP 47 language tag used to label day-of-week patterns (e.g. "de", "pt-BR")
+    pub locale: Option<Locale>,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+/// Named range of hours, `start` inclusive and `end` exclusive.
+/// A range whose end is before its start wraps past midnight.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "RawTimeOfDayRange")]
+pub struct TimeOfDayRange {
+    pub name: String,
+    pub start: u32,
+    pub end: u32,
+}
+
+#[derive(Deserialize)]
+struct RawTimeOfDayRange {
+    name: String,
+    start: u32,
+    end: u32,
+}
+
+impl TryFrom<RawTimeOfDayRange> for TimeOfDayRange {
+    type Error = String;
+
+    fn try_from(raw: RawTimeOfDayRange) -> Result<Self, Self::Error> {
+        if raw.name.is_empty() {
+            return Err("time-of-day range name must not be empty".to_string());
+        }
+        if raw.start > 23 || raw.end > 24 || raw.start == raw.end {
+            return Err(format!(
+                "invalid hours for time-of-day range `{}`: start must be 0-23, end 0-24 and different from start",
+                raw.name
+            ));
+        }
+        Ok(TimeOfDayRange {
+            name: raw.name,
+            start: raw.start,
+            end: raw.end,
+        })
+    }
+}
+
+impl TimeOfDayRange {
+    fn new(name: &str, start: u32, end: u32) -> Self {
+        TimeOfDayRange {
+            name: name.to_string(),
+            start,
+            end,
+        }
+    }
+
+    fn contains(&self, hour: u32) -> bool {
+        if self.start < self.end {
+            (self.start..self.end).contains(&hour)
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+fn default_time_of_day_ranges() -> Vec<TimeOfDayRange> {
+    vec![
+        TimeOfDayRange::new("morning", 5, 12),
+        TimeOfDayRange::new("afternoon", 12, 17),
+        TimeOfDayRange::new("evening", 17, 22),
+        TimeOfDayRange::new("night", 22, 5),
+    ]
+}
+
+/// Day-name table resolved from a BCP 47 language tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+    day_names: &'static [&'static str; 7],
+}
+
+impl Locale {
+    /// Resolve a language tag by its primary language subtag ("pt-BR" -> "pt")
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let language = tag.split(['-', '_']).next()?.to_ascii_lowercase();
+        DAY_NAME_LOCALES
+            .iter()
+            .find(|(code, _)| *code == language)
+            .map(|(_, day_names)| Locale { day_names })
+    }
+}
+
+impl<'de> Deserialize<'de> for Locale {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tag = String::deserialize(deserializer)?;
+        Locale::parse(&tag)
+            .ok_or_else(|| serde::de::Error::custom(format!("unsupported locale `{}`", tag)))
+    }
+}
 
 /// Compute time patterns from reflections
 pub fn compute_time_patterns(
     reflections: &[Reflection],
+    options: &TimePatternsOptions,
 ) -> TimePatternsResult {
     let mut day_of_week_map: HashMap<String, PatternData> = HashMap::new();
     let mut time_of_day_map: HashMap<String, PatternData> = HashMap::new();
     let mut month_map: HashMap<String, PatternData> = HashMap::new();
+    let mut hour_map: HashMap<String, PatternData> = HashMap::new();
+
+    let ranges = resolve_ranges(options);
 
-    for reflection in reflections {
+    let reflections = apply_filter(reflections, options.filter.as_ref(), options.timezone.as_ref());
+    for reflection in reflections.iter() {
         // Parse timestamp
-        let timestamp = match parse_timestamp(&reflection.timestamp) {
+        let timestamp = match parse_timestamp(&reflection.timestamp, options.timezone.as_ref()) {
             Some(ts) => ts,
             None => continue,
         };
 
-        let day_of_week = DAY_NAMES[timestamp.weekday() as usize];
-        let hour = timestamp.hour();
-        let time_of_day = if hour >= 5 && hour < 12 {
-            "morning"
-        } else if hour >= 12 && hour < 17 {
-            "afternoon"
-        } else if hour >= 17 && hour < 22 {
-            "evening"
-        } else {
-            "night"
-        };
-        let month = format!("{:04}-{:02}", timestamp.year(), timestamp.month());
+        let (day_of_week, hour, month) = pattern_keys(&timestamp);
+        // Hours outside every configured range are left out of time-of-day patterns
+        let time_of_day = ranges
+            .iter()
+            .find(|range| range.contains(timestamp.hour))
+            .map(|range| range.name.as_str());
 
         let emotion_id = reflection.emotion_id.clone().unwrap_or_else(|| "unknown".to_string());
         let emotion_name = reflection.emotion_name.clone().unwrap_or_else(|| "Unknown".to_string());
@@ -41,56 +176,252 @@ pub fn compute_time_patterns(
         // Update day of week
         update_pattern_data(
             &mut day_of_week_map,
-            day_of_week,
+            day_of_week.to_string(),
             &emotion_id,
             &emotion_name,
             reflection.intensity,
         );
 
         // Update time of day
-        update_pattern_data(
-            &mut time_of_day_map,
-            time_of_day,
-            &emotion_id,
-            &emotion_name,
-            reflection.intensity,
-        );
+        if let Some(time_of_day) = time_of_day {
+            update_pattern_data(
+                &mut time_of_day_map,
+                time_of_day.to_string(),
+                &emotion_id,
+                &emotion_name,
+                reflection.intensity,
+            );
+        }
+
+        // Update hour of day
+        if options.include_hours {
+            update_pattern_data(
+                &mut hour_map,
+                hour.to_string(),
+                &emotion_id,
+                &emotion_name,
+                reflection.intensity,
+            );
+        }
 
         // Update month
         update_pattern_data(
             &mut month_map,
-            &month,
+            month,
             &emotion_id,
             &emotion_name,
             reflection.intensity,
         );
     }
 
+    format_time_patterns(
+        day_of_week_map,
+        time_of_day_map,
+        month_map,
+        options.include_hours.then_some(hour_map),
+        options,
+    )
+}
+
+/// Time pattern buckets of a growing and shrinking set of reflections
+///
+/// Reflections are bucketed in their own wall clock, by the default
+/// time-of-day ranges and by hour, so a query without a timezone or filter
+/// is answered from the buckets alone; custom time-of-day ranges are
+/// regrouped from the hour buckets.
+#[derive(Default)]
+pub struct TimePatternAggregates {
+    day_of_week: HashMap<String, PatternData>,
+    time_of_day: HashMap<String, PatternData>,
+    hour_of_day: HashMap<String, PatternData>,
+    month: HashMap<String, PatternData>,
+}
+
+impl TimePatternAggregates {
+    /// Add one reflection to its buckets
+    pub fn add(&mut self, reflection: &Reflection) {
+        let timestamp = match parse_timestamp(&reflection.timestamp, None) {
+            Some(ts) => ts,
+            None => return,
+        };
+        let emotion_id = reflection.emotion_id.as_deref().unwrap_or("unknown");
+        let emotion_name = reflection.emotion_name.as_deref().unwrap_or("Unknown");
+        let intensity = reflection.intensity;
+
+        for (map, key) in self.buckets(&timestamp) {
+            update_pattern_data(map, key, emotion_id, emotion_name, intensity);
+        }
+    }
+
+    /// Take one previously added reflection back out of its buckets
+    pub fn remove(&mut self, reflection: &Reflection) {
+        let timestamp = match parse_timestamp(&reflection.timestamp, None) {
+            Some(ts) => ts,
+            None => return,
+        };
+        let emotion_id = reflection.emotion_id.as_deref().unwrap_or("unknown");
+
+        for (map, key) in self.buckets(&timestamp) {
+            remove_pattern_data(map, &key, emotion_id, reflection.intensity);
+        }
+    }
+
+    /// Answer a query from the buckets, or `None` when its timezone or filter needs a full pass
+    pub fn query(&self, options: &TimePatternsOptions) -> Option<TimePatternsResult> {
+        if options.timezone.is_some() || options.filter.is_some() {
+            return None;
+        }
+
+        let time_of_day = match &options.time_of_day {
+            None => self.time_of_day.clone(),
+            Some(ranges) => {
+                let mut map: HashMap<String, PatternData> = HashMap::new();
+                for (hour, key) in HOUR_NAMES.iter().enumerate() {
+                    let range = ranges.iter().find(|range| range.contains(hour as u32));
+                    if let (Some(range), Some(data)) = (range, self.hour_of_day.get(*key)) {
+                        map.entry(range.name.clone()).or_default().merge(data);
+                    }
+                }
+                map
+            }
+        };
+
+        Some(format_time_patterns(
+            self.day_of_week.clone(),
+            time_of_day,
+            self.month.clone(),
+            options.include_hours.then(|| self.hour_of_day.clone()),
+            options,
+        ))
+    }
+
+    /// Every bucket a timestamp belongs to, with its key
+    fn buckets(&mut self, timestamp: &DateTime) -> Vec<(&mut HashMap<String, PatternData>, String)> {
+        let (day_of_week, hour, month) = pattern_keys(timestamp);
+        let mut buckets = vec![
+            (&mut self.day_of_week, day_of_week.to_string()),
+            (&mut self.hour_of_day, hour.to_string()),
+            (&mut self.month, month),
+        ];
+        if let Some(range) = default_time_of_day_ranges()
+            .into_iter()
+            .find(|range| range.contains(timestamp.hour))
+        {
+            buckets.push((&mut self.time_of_day, range.name));
+        }
+        buckets
+    }
+}
+
+/// Day-of-week, hour and month keys of a timestamp
+fn pattern_keys(timestamp: &DateTime) -> (&'static str, &'static str, String) {
+    (
+        DAY_NAMES[timestamp.weekday() as usize],
+        HOUR_NAMES[timestamp.hour as usize],
+        format!("{:04}-{:02}", timestamp.year, timestamp.month),
+    )
+}
+
+/// Time-of-day ranges of a query, falling back to the defaults
+fn resolve_ranges(options: &TimePatternsOptions) -> Cow<'_, [TimeOfDayRange]> {
+    match &options.time_of_day {
+        Some(ranges) => Cow::Borrowed(ranges),
+        None => Cow::Owned(default_time_of_day_ranges()),
+    }
+}
+
+/// Format bucketed patterns; hour buckets are emitted only when given
+fn format_time_patterns(
+    day_of_week_map: HashMap<String, PatternData>,
+    time_of_day_map: HashMap<String, PatternData>,
+    month_map: HashMap<String, PatternData>,
+    hour_map: Option<HashMap<String, PatternData>>,
+    options: &TimePatternsOptions,
+) -> TimePatternsResult {
+    let top_n = options.top_n.unwrap_or(DEFAULT_TOP_EMOTIONS);
+
+    let mut day_of_week = format_patterns(day_of_week_map, &DAY_NAMES, top_n);
+    if let Some(locale) = &options.locale {
+        for pattern in &mut day_of_week {
+            pattern.label = DAY_NAMES
+                .iter()
+                .position(|&day| day == pattern.period)
+                .map(|idx| locale.day_names[idx].to_string());
+        }
+    }
+
+    let ranges = resolve_ranges(options);
+    let range_names: Vec<&str> = ranges.iter().map(|range| range.name.as_str()).collect();
+
     TimePatternsResult {
-        day_of_week: format_patterns(day_of_week_map, &DAY_NAMES),
-        time_of_day: format_patterns(time_of_day_map, &TIME_OF_DAY_NAMES),
-        month: format_patterns(month_map, &[]),
+        day_of_week,
+        time_of_day: format_patterns(time_of_day_map, &range_names, top_n),
+        month: format_patterns(month_map, &[], top_n),
+        hour_of_day: hour_map.map(|hour_map| format_patterns(hour_map, &HOUR_NAMES, top_n)),
     }
 }
 
-struct PatternData {
-    count: usize,
+/// Count, intensities, mood deltas and emotion tallies for one pattern bucket
+#[derive(Clone, Default)]
+pub struct PatternData {
+    pub count: usize,
     intensities: Vec<f64>,
+    pub mood_deltas: Vec<f64>,
     emotions: HashMap<String, (String, usize)>, // emotion_id -> (emotion_name, count)
 }
 
-fn update_pattern_data(
-    map: &mut HashMap<String, PatternData>,
-    period: &str,
+impl PatternData {
+    pub fn average_intensity(&self) -> Option<f64> {
+        mean(&self.intensities)
+    }
+
+    pub fn mean_mood_delta(&self) -> Option<f64> {
+        mean(&self.mood_deltas)
+    }
+
+    /// Most frequent emotions first, ties broken by emotion id
+    pub fn top_emotions(&self, top_n: usize) -> Vec<EmotionCount> {
+        let mut top_emotions: Vec<EmotionCount> = self
+            .emotions
+            .iter()
+            .map(|(emotion_id, (emotion_name, count))| EmotionCount {
+                emotion_id: emotion_id.clone(),
+                emotion_name: emotion_name.clone(),
+                count: *count,
+            })
+            .collect();
+        top_emotions.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then_with(|| a.emotion_id.cmp(&b.emotion_id))
+        });
+        top_emotions.truncate(top_n);
+        top_emotions
+    }
+
+    /// Fold another bucket into this one
+    fn merge(&mut self, other: &PatternData) {
+        self.count += other.count;
+        self.intensities.extend_from_slice(&other.intensities);
+        self.mood_deltas.extend_from_slice(&other.mood_deltas);
+        for (emotion_id, (emotion_name, count)) in &other.emotions {
+            self.emotions
+                .entry(emotion_id.clone())
+                .or_insert_with(|| (emotion_name.clone(), 0))
+                .1 += count;
+        }
+    }
+}
+
+/// Record one reflection under `key`, returning the bucket for further updates
+pub fn update_pattern_data<'a, K: Eq + Hash>(
+    map: &'a mut HashMap<K, PatternData>,
+    key: K,
     emotion_id: &str,
     emotion_name: &str,
     intensity: Option<f64>,
-) {
-    let data = map.entry(period.to_string()).or_insert_with(|| PatternData {
-        count: 0,
-        intensities: Vec::new(),
-        emotions: HashMap::new(),
-    });
+) -> &'a mut PatternData {
+    let data = map.entry(key).or_default();
 
     data.count += 1;
     if let Some(int) = intensity {
@@ -101,39 +432,53 @@ fn update_pattern_data(
         .entry(emotion_id.to_string())
         .or_insert_with(|| (emotion_name.to_string(), 0));
     emotion_entry.1 += 1;
+    data
+}
+
+/// Undo one `update_pattern_data` call, dropping the bucket once it is empty
+///
+/// Mood deltas are left alone; callers that record them remove them too.
+pub fn remove_pattern_data<K: Eq + Hash>(
+    map: &mut HashMap<K, PatternData>,
+    key: &K,
+    emotion_id: &str,
+    intensity: Option<f64>,
+) {
+    let data = match map.get_mut(key) {
+        Some(data) => data,
+        None => return,
+    };
+
+    data.count -= 1;
+    if let Some(int) = intensity {
+        if let Some(idx) = data.intensities.iter().position(|v| v.to_bits() == int.to_bits()) {
+            data.intensities.remove(idx);
+        }
+    }
+    if let Some(emotion_entry) = data.emotions.get_mut(emotion_id) {
+        emotion_entry.1 -= 1;
+        if emotion_entry.1 == 0 {
+            data.emotions.remove(emotion_id);
+        }
+    }
+    if data.count == 0 {
+        map.remove(key);
+    }
 }
 
 fn format_patterns(
     map: HashMap<String, PatternData>,
     order: &[&str],
+    top_n: usize,
 ) -> Vec<TimePattern> {
     let mut patterns: Vec<TimePattern> = map
         .into_iter()
-        .map(|(period, data)| {
-            let average_intensity = if !data.intensities.is_empty() {
-                Some(data.intensities.iter().sum::<f64>() / data.intensities.len() as f64)
-            } else {
-                None
-            };
-
-            let mut top_emotions: Vec<EmotionCount> = data
-                .emotions
-                .into_iter()
-                .map(|(emotion_id, (emotion_name, count))| EmotionCount {
-                    emotion_id,
-                    emotion_name,
-                    count,
-                })
-                .collect();
-            top_emotions.sort_by(|a, b| b.count.cmp(&a.count));
-            top_emotions.truncate(5);
-
-            TimePattern {
-                period,
-                count: data.count,
-                average_intensity,
-                top_emotions,
-            }
+        .map(|(period, data)| TimePattern {
+            period,
+            label: None,
+            count: data.count,
+            average_intensity: data.average_intensity(),
+            top_emotions: data.top_emotions(top_n),
         })
         .collect();
 
@@ -150,205 +495,127 @@ fn format_patterns(
             }
         });
     } else {
-        patterns.sort_by(|a, b| b.count.cmp(&a.count));
+        patterns.sort_by_key(|p| std::cmp::Reverse(p.count));
     }
 
     patterns
 }
 
-/// Simple timestamp parser (ISO 8601 format)
-fn parse_timestamp(ts: &str) -> Option<SimpleDateTime> {
-    // Try to parse ISO 8601 format: "2024-01-15T10:00:00Z" or "2024-01-15T10:00:00.000Z"
-    let parts: Vec<&str> = ts.split('T').collect();
-    if parts.len() != 2 {
-        return None;
-    }
-
-    let date_parts: Vec<&str> = parts[0].split('-').collect();
-    if date_parts.len() != 3 {
-        return None;
-    }
-
-    let year = date_parts[0].parse::<i32>().ok()?;
-    let month = date_parts[1].parse::<u32>().ok()?;
-    let day = date_parts[2].parse::<u32>().ok()?;
-
-    // Validate month and day ranges
-    if month < 1 || month > 12 {
-        return None;
-    }
-    let max_day = days_in_month(year, month);
-    if day < 1 || day > max_day {
-        return None;
-    }
-
-    let time_part = parts[1].trim_end_matches('Z');
-    // Strip fractional seconds (e.g., ".000" from "10:00:00.000Z")
-    let time_part = time_part.split('.').next().unwrap_or(time_part);
-    let time_parts: Vec<&str> = time_part.split(':').collect();
-    if time_parts.len() < 2 {
-        return None;
-    }
-
-    let hour = time_parts[0].parse::<u32>().ok()?;
-    let minute = time_parts.get(1)?.parse::<u32>().ok()?;
-
-    // Validate hour and minute ranges
-    if hour > 23 || minute > 59 {
-        return None;
-    }
-
-    // Calculate weekday (simplified - using Zeller's congruence)
-    let weekday = calculate_weekday(year, month, day);
-
-    Some(SimpleDateTime {
-        year,
-        month,
-        day,
-        hour,
-        minute,
-        weekday,
-    })
-}
-
-/// Return the number of days in a given month, accounting for leap years
-fn days_in_month(year: i32, month: u32) -> u32 {
-    match month {
-        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
-        4 | 6 | 9 | 11 => 30,
-        2 => {
-            if is_leap_year(year) { 29 } else { 28 }
-        }
-        _ => 0,
-    }
-}
-
-/// Check if a year is a leap year
-fn is_leap_year(year: i32) -> bool {
-    (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0)
-}
-
-#[allow(dead_code)]
-struct SimpleDateTime {
-    year: i32,
-    month: u32,
-    day: u32,
-    hour: u32,
-    minute: u32,
-    weekday: u32, // 0 = Sunday, 6 = Saturday
-}
-
-impl SimpleDateTime {
-    fn weekday(&self) -> u32 {
-        self.weekday
-    }
-
-    fn hour(&self) -> u32 {
-        self.hour
-    }
-
-    fn year(&self) -> i32 {
-        self.year
-    }
-
-    fn month(&self) -> u32 {
-        self.month
-    }
-
-    #[cfg(test)]
-    fn day(&self) -> u32 {
-        self.day
-    }
-
-    #[cfg(test)]
-    fn minute(&self) -> u32 {
-        self.minute
-    }
-}
-
-/// Calculate weekday using Zeller's congruence
-/// Returns 0=Sunday, 1=Monday, ..., 6=Saturday
-fn calculate_weekday(year: i32, month: u32, day: u32) -> u32 {
-    let mut y = year;
-    let mut m = month as i32;
-    if m < 3 {
-        m += 12;
-        y -= 1;
-    }
-    let k = y % 100;
-    let j = y / 100;
-    let h = ((day as i32 + (13 * (m + 1)) / 5 + k + k / 4 + j / 4 - 2 * j) % 7 + 7) % 7;
-    ((h + 6) % 7) as u32 // Convert Zeller (0=Sat) to 0=Sunday, 6=Saturday
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_parse_timestamp() {
-        let ts = parse_timestamp("2024-01-15T10:00:00Z");
-        assert!(ts.is_some());
-        let dt = ts.unwrap();
-        assert_eq!(dt.year(), 2024);
-        assert_eq!(dt.month(), 1);
-        assert_eq!(dt.day(), 15);
-        assert_eq!(dt.hour(), 10);
-        assert_eq!(dt.minute(), 0);
-    }
-
-    #[test]
-    fn test_parse_timestamp_with_fractional_seconds() {
-        let ts = parse_timestamp("2024-01-15T10:30:00.000Z");
-        assert!(ts.is_some());
-        let dt = ts.unwrap();
-        assert_eq!(dt.hour(), 10);
-        assert_eq!(dt.minute(), 30);
+    fn test_compute_time_patterns_with_timezone() {
+        let reflection = Reflection {
+            id: None,
+            timestamp: "2024-07-15T03:00:00Z".to_string(),
+            emotion_id: Some("joy".to_string()),
+            emotion_name: Some("Joy".to_string()),
+            intensity: Some(5.0),
+            related_emotions: None,
+            location: None,
+            people: None,
+            coping_strategies: None,
+            mood_before: None,
+            mood_after: None,
+        };
+        let options = TimePatternsOptions {
+            timezone: TimeZone::parse("America/Los_Angeles"),
+            ..TimePatternsOptions::default()
+        };
+        // 03:00 UTC is 20:00 PDT the previous evening
+        let result = compute_time_patterns(&[reflection], &options);
+        assert_eq!(result.time_of_day[0].period, "evening");
+        assert_eq!(result.day_of_week[0].period, "sunday");
     }
 
-    #[test]
-    fn test_parse_timestamp_rejects_invalid_month() {
-        assert!(parse_timestamp("2024-13-15T10:00:00Z").is_none());
-        assert!(parse_timestamp("2024-00-15T10:00:00Z").is_none());
+    fn reflection_at(timestamp: &str, emotion_id: &str) -> Reflection {
+        Reflection {
+            id: None,
+            timestamp: timestamp.to_string(),
+            emotion_id: Some(emotion_id.to_string()),
+            emotion_name: None,
+            intensity: None,
+            related_emotions: None,
+            location: None,
+            people: None,
+            coping_strategies: None,
+            mood_before: None,
+            mood_after: None,
+        }
     }
 
     #[test]
-    fn test_parse_timestamp_rejects_invalid_day() {
-        assert!(parse_timestamp("2024-02-30T10:00:00Z").is_none());
-        assert!(parse_timestamp("2024-01-32T10:00:00Z").is_none());
-        assert!(parse_timestamp("2024-01-00T10:00:00Z").is_none());
+    fn test_default_time_of_day_ranges() {
+        let reflections = vec![
+            reflection_at("2024-01-15T04:59:00Z", "a"),
+            reflection_at("2024-01-15T05:00:00Z", "a"),
+            reflection_at("2024-01-15T22:00:00Z", "a"),
+        ];
+        let result = compute_time_patterns(&reflections, &TimePatternsOptions::default());
+        let periods: Vec<(&str, usize)> = result
+            .time_of_day
+            .iter()
+            .map(|p| (p.period.as_str(), p.count))
+            .collect();
+        assert_eq!(periods, vec![("morning", 1), ("night", 2)]);
+        assert!(result.hour_of_day.is_none());
     }
 
     #[test]
-    fn test_parse_timestamp_rejects_invalid_hour() {
-        assert!(parse_timestamp("2024-01-15T25:00:00Z").is_none());
+    fn test_custom_time_of_day_ranges() {
+        let options: TimePatternsOptions = serde_json::from_str(
+            r#"{"timeOfDay": [{"name": "night", "start": 0, "end": 6}, {"name": "day", "start": 6, "end": 24}]}"#,
+        )
+        .unwrap();
+        let reflections = vec![
+            reflection_at("2024-01-15T05:30:00Z", "a"),
+            reflection_at("2024-01-15T23:00:00Z", "a"),
+            reflection_at("2024-01-15T06:00:00Z", "a"),
+        ];
+        let result = compute_time_patterns(&reflections, &options);
+        assert_eq!(result.time_of_day[0].period, "night");
+        assert_eq!(result.time_of_day[0].count, 1);
+        assert_eq!(result.time_of_day[1].period, "day");
+        assert_eq!(result.time_of_day[1].count, 2);
     }
 
     #[test]
-    fn test_parse_timestamp_rejects_invalid_minute() {
-        assert!(parse_timestamp("2024-01-15T10:60:00Z").is_none());
+    fn test_invalid_time_of_day_range() {
+        let result = serde_json::from_str::<TimePatternsOptions>(
+            r#"{"timeOfDay": [{"name": "bad", "start": 25, "end": 3}]}"#,
+        );
+        assert!(result.is_err());
     }
 
     #[test]
-    fn test_leap_year_feb_29() {
-        // 2024 is a leap year
-        let ts = parse_timestamp("2024-02-29T10:00:00Z");
-        assert!(ts.is_some());
-        // 2023 is not a leap year
-        let ts = parse_timestamp("2023-02-29T10:00:00Z");
-        assert!(ts.is_none());
+    fn test_top_n_and_hours() {
+        let reflections: Vec<Reflection> = (0..12)
+            .map(|i| reflection_at(&format!("2024-01-15T{:02}:00:00Z", i), &format!("e{}", i)))
+            .collect();
+        let options = TimePatternsOptions {
+            top_n: Some(10),
+            include_hours: true,
+            ..TimePatternsOptions::default()
+        };
+        let result = compute_time_patterns(&reflections, &options);
+        assert_eq!(result.day_of_week[0].top_emotions.len(), 10);
+        let hours = result.hour_of_day.unwrap();
+        assert_eq!(hours.len(), 12);
+        assert_eq!(hours[0].period, "00");
+        assert_eq!(hours[11].period, "11");
     }
 
     #[test]
-    fn test_calculate_weekday() {
-        // January 15, 2024 is a Monday (1)
-        let weekday = calculate_weekday(2024, 1, 15);
-        assert_eq!(weekday, 1);
-        // January 14, 2024 is a Sunday (0)
-        let weekday = calculate_weekday(2024, 1, 14);
-        assert_eq!(weekday, 0);
-        // January 20, 2024 is a Saturday (6)
-        let weekday = calculate_weekday(2024, 1, 20);
-        assert_eq!(weekday, 6);
+    fn test_locale_day_labels() {
+        let options = TimePatternsOptions {
+            locale: Locale::parse("de-DE"),
+            ..TimePatternsOptions::default()
+        };
+        let result = compute_time_patterns(&[reflection_at("2024-01-15T10:00:00Z", "a")], &options);
+        assert_eq!(result.day_of_week[0].period, "monday");
+        assert_eq!(result.day_of_week[0].label.as_deref(), Some("Montag"));
+        assert!(Locale::parse("xx").is_none());
     }
 }
@@ -0,0 +1,419 @@
+use serde::{Deserialize, Deserializer};
+
+use super::datetime::{
+    civil_from_days, days_from_civil, is_leap_year, parse_utc_offset, weekday_of, DateTime,
+    SECONDS_PER_DAY,
+};
+
+// Shared POSIX rules for zones that follow the same transitions
+const US_EASTERN: &str = "EST5EDT,M3.2.0,M11.1.0";
+const US_CENTRAL: &str = "CST6CDT,M3.2.0,M11.1.0";
+const US_MOUNTAIN: &str = "MST7MDT,M3.2.0,M11.1.0";
+const US_PACIFIC: &str = "PST8PDT,M3.2.0,M11.1.0";
+const EU_CENTRAL: &str = "CET-1CEST,M3.5.0,M10.5.0/3";
+const EU_EASTERN: &str = "EET-2EEST,M3.5.0/3,M10.5.0/4";
+const AU_EASTERN: &str = "AEST-10AEDT,M10.1.0,M4.1.0/3";
+
+/// Embedded IANA zone table (name -> POSIX TZ rule), sorted by name for binary search.
+/// Rules describe current transitions only, which keeps the table small enough
+/// to ship inside the WASM binary.
+const ZONES: &[(&str, &str)] = &[
+    ("Africa/Abidjan", "GMT0"),
+    ("Africa/Accra", "GMT0"),
+    ("Africa/Algiers", "CET-1"),
+    ("Africa/Cairo", "EET-2EEST,M4.5.5/0,M10.5.4/24"),
+    ("Africa/Casablanca", "<+01>-1"),
+    ("Africa/Johannesburg", "SAST-2"),
+    ("Africa/Lagos", "WAT-1"),
+    ("Africa/Nairobi", "EAT-3"),
+    ("America/Anchorage", "AKST9AKDT,M3.2.0,M11.1.0"),
+    ("America/Argentina/Buenos_Aires", "<-03>3"),
+    ("America/Bogota", "<-05>5"),
+    ("America/Caracas", "<-04>4"),
+    ("America/Chicago", US_CENTRAL),
+    ("America/Denver", US_MOUNTAIN),
+    ("America/Edmonton", US_MOUNTAIN),
+    ("America/Guatemala", "CST6"),
+    ("America/Halifax", "AST4ADT,M3.2.0,M11.1.0"),
+    ("America/Havana", "CST5CDT,M3.2.0/0,M11.1.0/1"),
+    ("America/Lima", "<-05>5"),
+    ("America/Los_Angeles", US_PACIFIC),
+    ("America/Mexico_City", "CST6"),
+    ("America/Montevideo", "<-03>3"),
+    ("America/New_York", US_EASTERN),
+    ("America/Panama", "EST5"),
+    ("America/Phoenix", "MST7"),
+    ("America/Puerto_Rico", "AST4"),
+    ("America/Regina", "CST6"),
+    ("America/Santiago", "<-04>4<-03>,M9.1.6/24,M4.1.6/24"),
+    ("America/Sao_Paulo", "<-03>3"),
+    ("America/St_Johns", "NST3:30NDT,M3.2.0,M11.1.0"),
+    ("America/Toronto", US_EASTERN),
+    ("America/Vancouver", US_PACIFIC),
+    ("America/Winnipeg", US_CENTRAL),
+    ("Asia/Almaty", "<+05>-5"),
+    ("Asia/Baghdad", "<+03>-3"),
+    ("Asia/Bangkok", "<+07>-7"),
+    ("Asia/Beirut", "EET-2EEST,M3.5.0/0,M10.5.0/0"),
+    ("Asia/Dhaka", "<+06>-6"),
+    ("Asia/Dubai", "<+04>-4"),
+    ("Asia/Ho_Chi_Minh", "<+07>-7"),
+    ("Asia/Hong_Kong", "HKT-8"),
+    ("Asia/Jakarta", "WIB-7"),
+    ("Asia/Jerusalem", "IST-2IDT,M3.4.4/26,M10.5.0"),
+    ("Asia/Kabul", "<+0430>-4:30"),
+    ("Asia/Karachi", "PKT-5"),
+    ("Asia/Kathmandu", "<+0545>-5:45"),
+    ("Asia/Kolkata", "IST-5:30"),
+    ("Asia/Kuala_Lumpur", "<+08>-8"),
+    ("Asia/Manila", "PST-8"),
+    ("Asia/Riyadh", "<+03>-3"),
+    ("Asia/Seoul", "KST-9"),
+    ("Asia/Shanghai", "CST-8"),
+    ("Asia/Singapore", "<+08>-8"),
+    ("Asia/Taipei", "CST-8"),
+    ("Asia/Tashkent", "<+05>-5"),
+    ("Asia/Tehran", "<+0330>-3:30"),
+    ("Asia/Tokyo", "JST-9"),
+    ("Asia/Vladivostok", "<+10>-10"),
+    ("Asia/Yangon", "<+0630>-6:30"),
+    ("Asia/Yekaterinburg", "<+05>-5"),
+    ("Atlantic/Azores", "<-01>1<+00>,M3.5.0/0,M10.5.0/1"),
+    ("Atlantic/Reykjavik", "GMT0"),
+    ("Australia/Adelaide", "ACST-9:30ACDT,M10.1.0,M4.1.0/3"),
+    ("Australia/Brisbane", "AEST-10"),
+    ("Australia/Darwin", "ACST-9:30"),
+    ("Australia/Hobart", AU_EASTERN),
+    ("Australia/Melbourne", AU_EASTERN),
+    ("Australia/Perth", "AWST-8"),
+    ("Australia/Sydney", AU_EASTERN),
+    ("Etc/GMT", "GMT0"),
+    ("Etc/UTC", "UTC0"),
+    ("Europe/Amsterdam", EU_CENTRAL),
+    ("Europe/Athens", EU_EASTERN),
+    ("Europe/Berlin", EU_CENTRAL),
+    ("Europe/Brussels", EU_CENTRAL),
+    ("Europe/Bucharest", EU_EASTERN),
+    ("Europe/Budapest", EU_CENTRAL),
+    ("Europe/Copenhagen", EU_CENTRAL),
+    ("Europe/Dublin", "IST-1GMT0,M10.5.0,M3.5.0/1"),
+    ("Europe/Helsinki", EU_EASTERN),
+    ("Europe/Istanbul", "<+03>-3"),
+    ("Europe/Kyiv", EU_EASTERN),
+    ("Europe/Lisbon", "WET0WEST,M3.5.0/1,M10.5.0"),
+    ("Europe/London", "GMT0BST,M3.5.0/1,M10.5.0"),
+    ("Europe/Madrid", EU_CENTRAL),
+    ("Europe/Moscow", "MSK-3"),
+    ("Europe/Oslo", EU_CENTRAL),
+    ("Europe/Paris", EU_CENTRAL),
+    ("Europe/Prague", EU_CENTRAL),
+    ("Europe/Rome", EU_CENTRAL),
+    ("Europe/Stockholm", EU_CENTRAL),
+    ("Europe/Vienna", EU_CENTRAL),
+    ("Europe/Warsaw", EU_CENTRAL),
+    ("Europe/Zurich", EU_CENTRAL),
+    ("GMT", "GMT0"),
+    ("Pacific/Auckland", "NZST-12NZDT,M9.5.0,M4.1.0/3"),
+    ("Pacific/Fiji", "<+12>-12"),
+    ("Pacific/Guam", "ChST-10"),
+    ("Pacific/Honolulu", "HST10"),
+    ("UTC", "UTC0"),
+];
+
+/// Target timezone used to bucket reflections by local wall-clock time
+///
+/// Named zones apply today's rule to every date. Past changes to a zone's
+/// offset or DST schedule are not modelled, so timestamps from before the
+/// current rule took effect may land an hour or more away from the local
+/// time they were recorded in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeZone {
+    std_offset: i32, // seconds east of UTC
+    dst: Option<DstRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct DstRule {
+    offset: i32, // seconds east of UTC while DST is in effect
+    start: TransitionDate,
+    start_time: i32, // seconds after local midnight, in standard time
+    end: TransitionDate,
+    end_time: i32, // seconds after local midnight, in daylight time
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TransitionDate {
+    /// `Mm.w.d`: day `d` (0 = Sunday) of week `w` (5 = last) of month `m`
+    MonthWeekDay(u32, u32, u32),
+    /// `Jn`: day of year 1-365, never counting February 29
+    Julian(u32),
+    /// `n`: zero-based day of year 0-365, counting February 29
+    ZeroBased(u32),
+}
+
+impl TimeZone {
+    /// Resolve an IANA zone name, a POSIX TZ rule, or a fixed offset such as `+05:30`
+    pub fn parse(name: &str) -> Option<TimeZone> {
+        let name = name.trim();
+        if let Some(offset) = parse_utc_offset(name) {
+            return Some(TimeZone {
+                std_offset: offset,
+                dst: None,
+            });
+        }
+        if let Ok(idx) = ZONES.binary_search_by(|(zone, _)| zone.cmp(&name)) {
+            return parse_posix_tz(ZONES[idx].1);
+        }
+        parse_posix_tz(name)
+    }
+
+    /// UTC offset in seconds in effect at the given instant
+    pub fn offset_at(&self, utc_seconds: i64) -> i32 {
+        let rule = match &self.dst {
+            Some(rule) => rule,
+            None => return self.std_offset,
+        };
+
+        let (year, _, _) = civil_from_days((utc_seconds + self.std_offset as i64).div_euclid(SECONDS_PER_DAY));
+        let start = transition_day(year, rule.start) * SECONDS_PER_DAY + rule.start_time as i64
+            - self.std_offset as i64;
+        let end = transition_day(year, rule.end) * SECONDS_PER_DAY + rule.end_time as i64
+            - rule.offset as i64;
+
+        let in_dst = if start < end {
+            utc_seconds >= start && utc_seconds < end
+        } else {
+            // Southern hemisphere: DST spans the new year
+            utc_seconds < end || utc_seconds >= start
+        };
+
+        if in_dst {
+            rule.offset
+        } else {
+            self.std_offset
+        }
+    }
+
+    /// Local wall-clock time at the given instant
+    pub fn localize(&self, utc_seconds: i64) -> DateTime {
+        DateTime::from_epoch_seconds(utc_seconds, self.offset_at(utc_seconds))
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeZone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        TimeZone::parse(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown timezone `{}`", name)))
+    }
+}
+
+/// Day number (days since epoch) of a DST transition date in the given year
+fn transition_day(year: i32, date: TransitionDate) -> i64 {
+    let jan1 = days_from_civil(year, 1, 1);
+    match date {
+        TransitionDate::MonthWeekDay(month, week, weekday) => {
+            let first = days_from_civil(year, month, 1);
+            let first_weekday = weekday_of(first);
+            let mut day = first + ((weekday + 7 - first_weekday) % 7) as i64 + (week as i64 - 1) * 7;
+            let next_month = if month == 12 {
+                days_from_civil(year + 1, 1, 1)
+            } else {
+                days_from_civil(year, month + 1, 1)
+            };
+            while day >= next_month {
+                day -= 7;
+            }
+            day
+        }
+        TransitionDate::Julian(n) => {
+            let leap_shift = if is_leap_year(year) && n > 59 { 1 } else { 0 };
+            jan1 + n as i64 - 1 + leap_shift
+        }
+        TransitionDate::ZeroBased(n) => jan1 + n as i64,
+    }
+}
+
+/// Parse a POSIX TZ rule such as `CET-1CEST,M3.5.0,M10.5.0/3`
+fn parse_posix_tz(spec: &str) -> Option<TimeZone> {
+    let mut rest = spec;
+    skip_zone_name(&mut rest)?;
+    let std_offset = -parse_posix_time(&mut rest)?;
+
+    if rest.is_empty() {
+        return Some(TimeZone {
+            std_offset,
+            dst: None,
+        });
+    }
+
+    skip_zone_name(&mut rest)?;
+    let dst_offset = if rest.starts_with(',') || rest.is_empty() {
+        std_offset + 3600
+    } else {
+        -parse_posix_time(&mut rest)?
+    };
+
+    // Default to the US rules when a DST name is given without transitions
+    let rules = if rest.is_empty() { ",M3.2.0,M11.1.0" } else { rest };
+    let mut parts = rules.strip_prefix(',')?.split(',');
+    let (start, start_time) = parse_transition(parts.next()?)?;
+    let (end, end_time) = parse_transition(parts.next()?)?;
+    if parts.next().is_some() {
+        return None;
+    }
+
+    Some(TimeZone {
+        std_offset,
+        dst: Some(DstRule {
+            offset: dst_offset,
+            start,
+            start_time,
+            end,
+            end_time,
+        }),
+    })
+}
+
+/// Skip an alphabetic (`CET`) or quoted (`<+0530>`) zone abbreviation
+fn skip_zone_name(rest: &mut &str) -> Option<()> {
+    let len = if let Some(quoted) = rest.strip_prefix('<') {
+        quoted.find('>')? + 2
+    } else {
+        rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len())
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+/// Parse `[+-]hh[:mm[:ss]]` into seconds, advancing past it
+fn parse_posix_time(rest: &mut &str) -> Option<i32> {
+    let end = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == ':' || c == '+' || c == '-'))
+        .unwrap_or(rest.len());
+    let (token, remainder) = rest.split_at(end);
+    *rest = remainder;
+
+    let (sign, digits) = match token.as_bytes().first()? {
+        b'-' => (-1, &token[1..]),
+        b'+' => (1, &token[1..]),
+        _ => (1, token),
+    };
+
+    let mut seconds = 0;
+    for (idx, part) in digits.split(':').enumerate() {
+        let value = part.parse::<i32>().ok()?;
+        seconds += match idx {
+            0 if value <= 167 => value * 3600,
+            1 | 2 if value <= 59 => value * if idx == 1 { 60 } else { 1 },
+            _ => return None,
+        };
+    }
+    Some(sign * seconds)
+}
+
+/// Parse a transition such as `M3.2.0/2`, `J60` or `59/-1`
+fn parse_transition(spec: &str) -> Option<(TransitionDate, i32)> {
+    let (date, time) = match spec.split_once('/') {
+        Some((date, time)) => {
+            let mut time = time;
+            let seconds = parse_posix_time(&mut time)?;
+            if !time.is_empty() {
+                return None;
+            }
+            (date, seconds)
+        }
+        None => (spec, 2 * 3600),
+    };
+
+    let date = if let Some(mwd) = date.strip_prefix('M') {
+        let fields: Vec<u32> = mwd
+            .split('.')
+            .map(|field| field.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        match fields[..] {
+            [m, w, d] if (1..=12).contains(&m) && (1..=5).contains(&w) && d <= 6 => {
+                TransitionDate::MonthWeekDay(m, w, d)
+            }
+            _ => return None,
+        }
+    } else if let Some(julian) = date.strip_prefix('J') {
+        match julian.parse::<u32>().ok()? {
+            n @ 1..=365 => TransitionDate::Julian(n),
+            _ => return None,
+        }
+    } else {
+        match date.parse::<u32>().ok()? {
+            n @ 0..=365 => TransitionDate::ZeroBased(n),
+            _ => return None,
+        }
+    };
+
+    Some((date, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+        days_from_civil(year, month, day) * SECONDS_PER_DAY + (hour * 3600 + minute * 60) as i64
+    }
+
+    fn wall_clock(dt: DateTime) -> (i32, u32, u32, u32, u32) {
+        (dt.year, dt.month, dt.day, dt.hour, dt.minute)
+    }
+
+    #[test]
+    fn test_zone_table_is_sorted() {
+        assert!(ZONES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(ZONES.iter().all(|(_, rule)| parse_posix_tz(rule).is_some()));
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("Z"), Some(0));
+        assert_eq!(parse_utc_offset("+02:00"), Some(7200));
+        assert_eq!(parse_utc_offset("-0530"), Some(-19800));
+        assert_eq!(parse_utc_offset("+05"), Some(18000));
+        assert_eq!(parse_utc_offset("+25:00"), None);
+        assert_eq!(parse_utc_offset("05:00"), None);
+    }
+
+    #[test]
+    fn test_new_york_dst_transitions() {
+        let tz = TimeZone::parse("America/New_York").unwrap();
+        // 2024-03-10 06:59 UTC is 01:59 EST, one minute later clocks jump to 03:00 EDT
+        let before = utc(2024, 3, 10, 6, 59);
+        assert_eq!(wall_clock(tz.localize(before)), (2024, 3, 10, 1, 59));
+        assert_eq!(wall_clock(tz.localize(before + 60)), (2024, 3, 10, 3, 0));
+        // 2024-11-03 06:00 UTC is 01:00 EST after falling back from 02:00 EDT
+        let fall = utc(2024, 11, 3, 6, 0);
+        assert_eq!(tz.offset_at(fall - 1), -4 * 3600);
+        assert_eq!(tz.offset_at(fall), -5 * 3600);
+    }
+
+    #[test]
+    fn test_southern_hemisphere_dst() {
+        let tz = TimeZone::parse("Australia/Sydney").unwrap();
+        assert_eq!(tz.offset_at(utc(2024, 1, 15, 0, 0)), 11 * 3600);
+        assert_eq!(tz.offset_at(utc(2024, 7, 15, 0, 0)), 10 * 3600);
+    }
+
+    #[test]
+    fn test_negative_dst_dublin() {
+        let tz = TimeZone::parse("Europe/Dublin").unwrap();
+        assert_eq!(tz.offset_at(utc(2024, 1, 15, 0, 0)), 0);
+        assert_eq!(tz.offset_at(utc(2024, 7, 15, 0, 0)), 3600);
+    }
+
+    #[test]
+    fn test_parse_fixed_and_unknown() {
+        assert_eq!(TimeZone::parse("+05:30").unwrap().offset_at(0), 19800);
+        assert_eq!(TimeZone::parse("Asia/Kolkata").unwrap().offset_at(0), 19800);
+        assert!(TimeZone::parse("Mars/Olympus_Mons").is_none());
+    }
+}
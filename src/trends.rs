@@ -1,23 +1,115 @@
 use super::{Reflection, TrendDataPoint, EmotionCount, TrendsResult};
+use super::datetime::{civil_from_days, days_from_civil, parse_timestamp, weekday_of, DateTime};
+use super::filter::{apply_filter, ReflectionFilter};
+use super::timezone::TimeZone;
+use serde::Deserialize;
 use std::collections::HashMap;
 
+/// Options for trend computation
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrendsOptions {
+    /// IANA zone, POSIX rule or fixed offset to bucket by; unset keeps each timestamp's own date
+    pub timezone: Option<TimeZone>,
+    /// Week numbering used for weekly keys
+    #[serde(default)]
+    pub week_start: WeekStart,
+    /// Emit zero-count periods between the first and last reflection
+    #[serde(default)]
+    pub fill_gaps: bool,
+    /// Explicit inclusive date range; reflections outside it are dropped and
+    /// every period inside it is filled, instead of the reflections' own extent
+    pub range: Option<DateRange>,
+    /// Reflections not matching this filter are ignored
+    pub filter: Option<ReflectionFilter>,
+}
+
+/// Longest accepted `range`, in days (about 100 years)
+pub const MAX_RANGE_DAYS: i64 = 36_600;
+
+/// Inclusive range of calendar dates, stored as day numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawDateRange")]
+pub struct DateRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+#[derive(Deserialize)]
+struct RawDateRange {
+    start: String,
+    end: String,
+}
+
+impl TryFrom<RawDateRange> for DateRange {
+    type Error = String;
+
+    fn try_from(raw: RawDateRange) -> Result<Self, Self::Error> {
+        let parse = |value: &str| {
+            DateTime::parse(value)
+                .map(|dt| dt.day_number())
+                .ok_or_else(|| format!("invalid date `{}`", value))
+        };
+        let range = DateRange {
+            start: parse(&raw.start)?,
+            end: parse(&raw.end)?,
+        };
+        if range.start > range.end {
+            return Err("range start must not be after range end".to_string());
+        }
+        if range.end - range.start >= MAX_RANGE_DAYS {
+            return Err(format!("range must not span more than {} days", MAX_RANGE_DAYS));
+        }
+        Ok(range)
+    }
+}
+
+impl DateRange {
+    pub fn contains(&self, day_number: i64) -> bool {
+        (self.start..=self.end).contains(&day_number)
+    }
+}
+
+/// Week numbering scheme for weekly trends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WeekStart {
+    /// ISO 8601: weeks start on Monday, week 1 contains the first Thursday
+    #[default]
+    #[serde(alias = "monday")]
+    Iso,
+    /// US: weeks start on Sunday, week 1 contains January 1
+    #[serde(alias = "sunday")]
+    Us,
+}
+
 /// Compute trends over time (daily, weekly, monthly)
 pub fn compute_trends(
     reflections: &[Reflection],
+    options: &TrendsOptions,
 ) -> TrendsResult {
     let mut daily_map: HashMap<String, TrendData> = HashMap::new();
     let mut weekly_map: HashMap<String, TrendData> = HashMap::new();
     let mut monthly_map: HashMap<String, TrendData> = HashMap::new();
+    let mut extent: Option<(i64, i64)> = None;
 
-    for reflection in reflections {
-        let timestamp = match parse_timestamp(&reflection.timestamp) {
+    let reflections = apply_filter(reflections, options.filter.as_ref(), options.timezone.as_ref());
+    for reflection in reflections.iter() {
+        let timestamp = match parse_timestamp(&reflection.timestamp, options.timezone.as_ref()) {
             Some(ts) => ts,
             None => continue,
         };
 
-        let daily = format!("{:04}-{:02}-{:02}", timestamp.year(), timestamp.month(), timestamp.day);
-        let weekly = get_week_key(timestamp.year(), timestamp.month(), timestamp.day);
-        let monthly = format!("{:04}-{:02}", timestamp.year(), timestamp.month());
+        let day_number = timestamp.day_number();
+        if options.range.is_some_and(|range| !range.contains(day_number)) {
+            continue;
+        }
+        extent = Some(match extent {
+            Some((first, last)) => (first.min(day_number), last.max(day_number)),
+            None => (day_number, day_number),
+        });
+
+        let (daily, weekly, monthly) = period_keys(day_number, options.week_start);
 
         let emotion_id = reflection.emotion_id.clone().unwrap_or_else(|| "unknown".to_string());
         let emotion_name = reflection.emotion_name.clone().unwrap_or_else(|| "Unknown".to_string());
@@ -32,6 +124,14 @@ pub fn compute_trends(
         update_trend_data(&mut monthly_map, &monthly, &emotion_id, &emotion_name, reflection.intensity);
     }
 
+    fill_periods(
+        [&mut daily_map, &mut weekly_map, &mut monthly_map],
+        options.range,
+        options.fill_gaps,
+        extent,
+        options.week_start,
+    );
+
     TrendsResult {
         daily: format_trends(daily_map),
         weekly: format_trends(weekly_map),
@@ -39,13 +139,130 @@ pub fn compute_trends(
     }
 }
 
-struct TrendData {
+/// Trend buckets of a growing and shrinking set of reflections
+///
+/// Reflections are bucketed in their own wall clock, with weekly buckets for
+/// both week numberings, so a query without a timezone, filter or explicit
+/// range is answered from the buckets alone.
+#[derive(Default)]
+pub struct TrendAggregates {
+    daily: HashMap<String, TrendData>,
+    weekly_iso: HashMap<String, TrendData>,
+    weekly_us: HashMap<String, TrendData>,
+    monthly: HashMap<String, TrendData>,
+}
+
+impl TrendAggregates {
+    /// Add one reflection to its buckets
+    pub fn add(&mut self, reflection: &Reflection) {
+        let timestamp = match parse_timestamp(&reflection.timestamp, None) {
+            Some(ts) => ts,
+            None => return,
+        };
+        let emotion_id = reflection.emotion_id.as_deref().unwrap_or("unknown");
+        let emotion_name = reflection.emotion_name.as_deref().unwrap_or("Unknown");
+
+        for (map, period) in self.buckets(timestamp.day_number()) {
+            update_trend_data(map, &period, emotion_id, emotion_name, reflection.intensity);
+        }
+    }
+
+    /// Take one previously added reflection back out of its buckets
+    pub fn remove(&mut self, reflection: &Reflection) {
+        let timestamp = match parse_timestamp(&reflection.timestamp, None) {
+            Some(ts) => ts,
+            None => return,
+        };
+        let emotion_id = reflection.emotion_id.as_deref().unwrap_or("unknown");
+
+        for (map, period) in self.buckets(timestamp.day_number()) {
+            remove_trend_data(map, &period, emotion_id, reflection.intensity);
+        }
+    }
+
+    /// Answer a query from the buckets, or `None` when its timezone, filter or range needs a full pass
+    pub fn query(&self, options: &TrendsOptions) -> Option<TrendsResult> {
+        if options.timezone.is_some() || options.filter.is_some() || options.range.is_some() {
+            return None;
+        }
+
+        let mut daily_map = self.daily.clone();
+        let mut weekly_map = match options.week_start {
+            WeekStart::Iso => self.weekly_iso.clone(),
+            WeekStart::Us => self.weekly_us.clone(),
+        };
+        let mut monthly_map = self.monthly.clone();
+
+        let mut days = self
+            .daily
+            .keys()
+            .filter_map(|date| DateTime::parse(date))
+            .map(|dt| dt.day_number());
+        let extent = days
+            .next()
+            .map(|first| days.fold((first, first), |(first, last), day| (first.min(day), last.max(day))));
+
+        fill_periods(
+            [&mut daily_map, &mut weekly_map, &mut monthly_map],
+            None,
+            options.fill_gaps,
+            extent,
+            options.week_start,
+        );
+
+        Some(TrendsResult {
+            daily: format_trends(daily_map),
+            weekly: format_trends(weekly_map),
+            monthly: format_trends(monthly_map),
+        })
+    }
+
+    /// Every bucket a day belongs to, with its period key
+    fn buckets(&mut self, day_number: i64) -> [(&mut HashMap<String, TrendData>, String); 4] {
+        let (daily, weekly_iso, monthly) = period_keys(day_number, WeekStart::Iso);
+        [
+            (&mut self.daily, daily),
+            (&mut self.weekly_iso, weekly_iso),
+            (&mut self.weekly_us, get_week_key(day_number, WeekStart::Us)),
+            (&mut self.monthly, monthly),
+        ]
+    }
+}
+
+/// Insert an empty bucket into the daily, weekly and monthly maps for every
+/// period of the explicit `range`, or of the data `extent` when only
+/// `fill_gaps` is set, so charts show inactivity as zero instead of interpolating
+pub fn fill_periods<T: Default>(
+    [daily_map, weekly_map, monthly_map]: [&mut HashMap<String, T>; 3],
+    range: Option<DateRange>,
+    fill_gaps: bool,
+    extent: Option<(i64, i64)>,
+    week_start: WeekStart,
+) {
+    let (start, end) = match range {
+        Some(range) => (range.start, range.end),
+        None if fill_gaps => match extent {
+            Some(extent) => extent,
+            None => return,
+        },
+        None => return,
+    };
+    for day_number in start..=end {
+        let (daily, weekly, monthly) = period_keys(day_number, week_start);
+        daily_map.entry(daily).or_default();
+        weekly_map.entry(weekly).or_default();
+        monthly_map.entry(monthly).or_default();
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct TrendData {
     count: usize,
     intensities: Vec<f64>,
     emotions: HashMap<String, (String, usize)>, // emotion_id -> (emotion_name, count)
 }
 
-fn update_trend_data(
+pub fn update_trend_data(
     map: &mut HashMap<String, TrendData>,
     period: &str,
     emotion_id: &str,
@@ -69,7 +286,36 @@ fn update_trend_data(
     emotion_entry.1 += 1;
 }
 
-fn format_trends(map: HashMap<String, TrendData>) -> Vec<TrendDataPoint> {
+/// Undo one `update_trend_data` call, dropping the period once it is empty
+pub fn remove_trend_data(
+    map: &mut HashMap<String, TrendData>,
+    period: &str,
+    emotion_id: &str,
+    intensity: Option<f64>,
+) {
+    let data = match map.get_mut(period) {
+        Some(data) => data,
+        None => return,
+    };
+
+    data.count -= 1;
+    if let Some(int) = intensity {
+        if let Some(idx) = data.intensities.iter().position(|v| v.to_bits() == int.to_bits()) {
+            data.intensities.remove(idx);
+        }
+    }
+    if let Some(emotion_entry) = data.emotions.get_mut(emotion_id) {
+        emotion_entry.1 -= 1;
+        if emotion_entry.1 == 0 {
+            data.emotions.remove(emotion_id);
+        }
+    }
+    if data.count == 0 {
+        map.remove(period);
+    }
+}
+
+pub fn format_trends(map: HashMap<String, TrendData>) -> Vec<TrendDataPoint> {
     let mut trends: Vec<TrendDataPoint> = map
         .into_iter()
         .map(|(date, data)| {
@@ -79,10 +325,13 @@ fn format_trends(map: HashMap<String, TrendData>) -> Vec<TrendDataPoint> {
                 None
             };
 
+            // Ties go to the smaller emotion id so output does not depend on hash order
             let top_emotion = data
                 .emotions
                 .into_iter()
-                .max_by_key(|(_, (_, count))| *count)
+                .max_by(|(a_id, (_, a_count)), (b_id, (_, b_count))| {
+                    a_count.cmp(b_count).then_with(|| b_id.cmp(a_id))
+                })
                 .map(|(emotion_id, (emotion_name, count))| EmotionCount {
                     emotion_id,
                     emotion_name,
@@ -104,89 +353,37 @@ fn format_trends(map: HashMap<String, TrendData>) -> Vec<TrendDataPoint> {
     trends
 }
 
-/// Get week key (YYYY-WW format) using ISO week numbering
-fn get_week_key(year: i32, month: u32, day: u32) -> String {
-    // Validate inputs
-    if month < 1 || month > 12 || day < 1 || day > 31 {
-        return format!("{:04}-W01", year);
-    }
-
-    // Calculate day of year
-    let days_in_months = [31u32, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
-    let is_leap = (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0);
-    let mut day_of_year = day;
-    for i in 0..(month - 1) as usize {
-        day_of_year += days_in_months[i];
-    }
-    // Add leap day for dates after February in a leap year
-    if is_leap && month > 2 {
-        day_of_year += 1;
-    }
-
-    // Clamp week to 1-53 range
-    let week = ((day_of_year as f64 / 7.0).ceil() as u32).max(1).min(53);
-    format!("{:04}-W{:02}", year, week)
+/// Daily (YYYY-MM-DD), weekly (YYYY-Www) and monthly (YYYY-MM) keys for a day number
+pub fn period_keys(day_number: i64, week_start: WeekStart) -> (String, String, String) {
+    let (year, month, day) = civil_from_days(day_number);
+    (
+        format!("{:04}-{:02}-{:02}", year, month, day),
+        get_week_key(day_number, week_start),
+        format!("{:04}-{:02}", year, month),
+    )
 }
 
-/// Simple timestamp parser for trends (only needs date components)
-fn parse_timestamp(ts: &str) -> Option<SimpleDateTime> {
-    let parts: Vec<&str> = ts.split('T').collect();
-    if parts.len() != 2 {
-        return None;
-    }
-
-    let date_parts: Vec<&str> = parts[0].split('-').collect();
-    if date_parts.len() != 3 {
-        return None;
-    }
-
-    let year = date_parts[0].parse::<i32>().ok()?;
-    let month = date_parts[1].parse::<u32>().ok()?;
-    let day = date_parts[2].parse::<u32>().ok()?;
-
-    // Validate month and day ranges
-    if month < 1 || month > 12 {
-        return None;
-    }
-    let max_day = match month {
-        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
-        4 | 6 | 9 | 11 => 30,
-        2 => {
-            let is_leap = (year % 4 == 0 && year % 100 != 0) || (year % 400 == 0);
-            if is_leap { 29 } else { 28 }
-        }
-        _ => return None,
+/// Get week key (YYYY-Www format) for the week containing the given day number
+///
+/// The week-year is the year holding the week's anchor day (Thursday for ISO
+/// weeks, Saturday for US weeks), so late-December dates can belong to week 1
+/// of the next year and early-January dates to the last week of the previous one.
+fn get_week_key(days: i64, week_start: WeekStart) -> String {
+    let (first_weekday, anchor) = match week_start {
+        WeekStart::Iso => (1, 3), // Monday start, Thursday anchor
+        WeekStart::Us => (0, 6),  // Sunday start, Saturday anchor
     };
-    if day < 1 || day > max_day {
-        return None;
-    }
-
-    Some(SimpleDateTime {
-        year,
-        month,
-        day,
-    })
-}
 
-struct SimpleDateTime {
-    year: i32,
-    month: u32,
-    day: u32,
-}
+    let week_start_day = days - ((weekday_of(days) + 7 - first_weekday) % 7) as i64;
+    let (week_year, _, _) = civil_from_days(week_start_day + anchor);
 
-impl SimpleDateTime {
-    fn year(&self) -> i32 {
-        self.year
-    }
+    // First week of the week-year is the one whose anchor day falls on or after January 1
+    let jan1 = days_from_civil(week_year, 1, 1);
+    let anchor_weekday = (first_weekday + anchor as u32) % 7;
+    let first_anchor = jan1 + ((anchor_weekday + 7 - weekday_of(jan1)) % 7) as i64;
+    let week = (week_start_day - (first_anchor - anchor)) / 7 + 1;
 
-    fn month(&self) -> u32 {
-        self.month
-    }
-
-    #[cfg(test)]
-    fn day(&self) -> u32 {
-        self.day
-    }
+    format!("{:04}-W{:02}", week_year, week)
 }
 
 #[cfg(test)]
@@ -195,45 +392,166 @@ mod tests {
 
     #[test]
     fn test_get_week_key() {
-        let key = get_week_key(2024, 1, 15);
+        let key = get_week_key(days_from_civil(2024, 1, 15), WeekStart::Iso);
         assert!(key.starts_with("2024-W"));
     }
 
     #[test]
     fn test_get_week_key_bounds() {
         // First day of year should be week 1
-        let key = get_week_key(2024, 1, 1);
+        let key = get_week_key(days_from_civil(2024, 1, 1), WeekStart::Iso);
         assert_eq!(key, "2024-W01");
-        // Last day of year should not exceed W53
-        let key = get_week_key(2024, 12, 31);
+        // Last week of the year should not exceed W53
+        let key = get_week_key(days_from_civil(2024, 12, 28), WeekStart::Iso);
         assert!(key.starts_with("2024-W"));
         let week_num: u32 = key[6..].parse().unwrap();
-        assert!(week_num >= 1 && week_num <= 53);
+        assert!((1..=53).contains(&week_num));
     }
 
     #[test]
     fn test_get_week_key_leap_year() {
         // March 1 in a leap year
-        let key = get_week_key(2024, 3, 1);
+        let key = get_week_key(days_from_civil(2024, 3, 1), WeekStart::Iso);
         assert!(key.starts_with("2024-W"));
     }
 
     #[test]
-    fn test_parse_timestamp_rejects_invalid() {
-        assert!(parse_timestamp("2024-13-15T10:00:00Z").is_none());
-        assert!(parse_timestamp("2024-00-15T10:00:00Z").is_none());
-        assert!(parse_timestamp("2024-02-30T10:00:00Z").is_none());
-        assert!(parse_timestamp("2024-01-00T10:00:00Z").is_none());
-        assert!(parse_timestamp("not-a-date").is_none());
+    fn test_get_week_key_iso_year_boundaries() {
+        // Late-December dates can belong to next year's W01
+        assert_eq!(get_week_key(days_from_civil(2024, 12, 30), WeekStart::Iso), "2025-W01");
+        // Early-January dates can belong to the previous year's last week
+        assert_eq!(get_week_key(days_from_civil(2021, 1, 3), WeekStart::Iso), "2020-W53");
+        assert_eq!(get_week_key(days_from_civil(2027, 1, 1), WeekStart::Iso), "2026-W53");
+        assert_eq!(get_week_key(days_from_civil(2024, 1, 15), WeekStart::Iso), "2024-W03");
+        // Monday starts a new ISO week
+        assert_eq!(get_week_key(days_from_civil(2024, 1, 14), WeekStart::Iso), "2024-W02");
     }
 
     #[test]
-    fn test_parse_timestamp_valid() {
-        let ts = parse_timestamp("2024-01-15T10:00:00Z");
-        assert!(ts.is_some());
-        let dt = ts.unwrap();
-        assert_eq!(dt.year(), 2024);
-        assert_eq!(dt.month(), 1);
-        assert_eq!(dt.day(), 15);
+    fn test_get_week_key_us() {
+        // Week containing January 1 is W01, starting on Sunday
+        assert_eq!(get_week_key(days_from_civil(2024, 12, 31), WeekStart::Us), "2025-W01");
+        assert_eq!(get_week_key(days_from_civil(2024, 12, 28), WeekStart::Us), "2024-W52");
+        assert_eq!(get_week_key(days_from_civil(2024, 1, 14), WeekStart::Us), "2024-W03");
+        assert_eq!(get_week_key(days_from_civil(2024, 1, 13), WeekStart::Us), "2024-W02");
+    }
+
+    fn reflection(timestamp: &str) -> Reflection {
+        Reflection {
+            timestamp: timestamp.to_string(),
+            emotion_id: Some("joy".to_string()),
+            emotion_name: Some("Joy".to_string()),
+            intensity: Some(4.0),
+            ..Reflection::test_default()
+        }
+    }
+
+    #[test]
+    fn test_compute_trends_skips_invalid_time() {
+        let reflections = vec![
+            reflection("2024-01-15Tgarbage"),
+            reflection("2024-02-30T10:00:00Z"),
+            reflection("2024-01-15"),
+            reflection("1705312800000"),
+        ];
+        let result = compute_trends(&reflections, &TrendsOptions::default());
+        assert_eq!(result.daily.len(), 1);
+        assert_eq!(result.daily[0].date, "2024-01-15");
+        assert_eq!(result.daily[0].count, 2);
+    }
+
+    #[test]
+    fn test_compute_trends_with_timezone() {
+        let reflection = reflection("2024-03-01T21:30:00-08:00");
+        let options = TrendsOptions {
+            timezone: TimeZone::parse("Asia/Tokyo"),
+            ..TrendsOptions::default()
+        };
+        let result = compute_trends(std::slice::from_ref(&reflection), &options);
+        assert_eq!(result.daily[0].date, "2024-03-02");
+        let result = compute_trends(&[reflection], &TrendsOptions::default());
+        assert_eq!(result.daily[0].date, "2024-03-01");
+    }
+
+    #[test]
+    fn test_fill_gaps() {
+        let reflections = vec![reflection("2024-01-30T10:00:00Z"), reflection("2024-03-02T10:00:00Z")];
+
+        let result = compute_trends(&reflections, &TrendsOptions::default());
+        assert_eq!(result.daily.len(), 2);
+
+        let options = TrendsOptions {
+            fill_gaps: true,
+            ..TrendsOptions::default()
+        };
+        let result = compute_trends(&reflections, &options);
+        // January 30 through March 2, 2024 (leap year)
+        assert_eq!(result.daily.len(), 33);
+        assert_eq!(result.daily[1].date, "2024-01-31");
+        assert_eq!(result.daily[1].count, 0);
+        assert!(result.daily[1].average_intensity.is_none());
+        assert!(result.daily[1].top_emotion.is_none());
+        assert_eq!(result.weekly.first().unwrap().date, "2024-W05");
+        assert_eq!(result.weekly.last().unwrap().date, "2024-W09");
+        assert_eq!(result.weekly.len(), 5);
+        let months: Vec<&str> = result.monthly.iter().map(|m| m.date.as_str()).collect();
+        assert_eq!(months, vec!["2024-01", "2024-02", "2024-03"]);
+    }
+
+    #[test]
+    fn test_fill_explicit_range() {
+        let options: TrendsOptions =
+            serde_json::from_str(r#"{"range": {"start": "2024-01-01", "end": "2024-01-07"}}"#).unwrap();
+        let result = compute_trends(&[], &options);
+        assert_eq!(result.daily.len(), 7);
+        assert!(result.daily.iter().all(|point| point.count == 0));
+        assert!(serde_json::from_str::<TrendsOptions>(
+            r#"{"range": {"start": "2024-02-01", "end": "2024-01-01"}}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<TrendsOptions>(
+            r#"{"range": {"start": "0001-01-01", "end": "9999-12-31"}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_filter_date_range_uses_bucketing_timezone() {
+        let reflection = reflection("2024-01-15T23:30:00-08:00");
+        let options: TrendsOptions = serde_json::from_str(
+            r#"{"timezone": "America/Los_Angeles", "filter": {"dateRange": {"end": "2024-01-15"}}}"#,
+        )
+        .unwrap();
+        let result = compute_trends(&[reflection], &options);
+        assert_eq!(result.daily.len(), 1);
+        assert_eq!(result.daily[0].date, "2024-01-15");
+    }
+
+    #[test]
+    fn test_explicit_range_drops_outside_reflections() {
+        let reflections = vec![
+            reflection("2023-12-31T10:00:00Z"),
+            reflection("2024-01-03T10:00:00Z"),
+            reflection("2024-01-08T10:00:00Z"),
+        ];
+        let options: TrendsOptions =
+            serde_json::from_str(r#"{"range": {"start": "2024-01-01", "end": "2024-01-07"}}"#).unwrap();
+        let result = compute_trends(&reflections, &options);
+        assert_eq!(result.daily.len(), 7);
+        assert_eq!(result.daily.first().unwrap().date, "2024-01-01");
+        assert_eq!(result.daily.last().unwrap().date, "2024-01-07");
+        assert_eq!(result.daily.iter().map(|point| point.count).sum::<usize>(), 1);
+        let months: Vec<&str> = result.monthly.iter().map(|m| m.date.as_str()).collect();
+        assert_eq!(months, vec!["2024-01"]);
+    }
+
+    #[test]
+    fn test_top_emotion_tie_prefers_smaller_id() {
+        let mut map = HashMap::new();
+        for emotion in ["sad", "calm", "joy"] {
+            update_trend_data(&mut map, "2024-01-15", emotion, emotion, None);
+        }
+        let trends = format_trends(map);
+        assert_eq!(trends[0].top_emotion.as_ref().unwrap().emotion_id, "calm");
     }
 }